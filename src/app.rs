@@ -1,8 +1,15 @@
+use std::f32::consts::FRAC_PI_2;
+use std::fs;
+use std::path::Path;
+use std::process;
 use std::sync::Arc;
 
-use glam::{Quat, Vec3};
+use anyhow::Result;
+use glam::{Mat3, Quat, Vec3};
 use gobs::scene::shape::Shapes;
 use hecs::World;
+use log::*;
+use rand::{rngs::StdRng, SeedableRng};
 
 use gobs::core::entity::{camera::Camera, instance::InstanceFlag, light::Light};
 use gobs::core::geometry::vertex::VertexFlag;
@@ -11,140 +18,232 @@ use gobs::scene::{
     Gfx, MaterialBuilder, Model, ModelBuilder, PipelineFlag, RenderError, Scene, Shader,
 };
 
-use crate::components::{self, Name, Orientation, Player, Position};
+use crate::components::{Action, Door, LightSource, Player, Position, Secret};
+use crate::config::Settings;
 use crate::events::Event;
-use crate::map::TileMap;
-use crate::movement::Facing;
-use crate::systems;
+use crate::gamepad::Gamepads;
+use crate::hud::{Element, Hud, MapTile, Pointer, Rect, View, Widget};
+use crate::lighting::Lighting;
+use crate::map::{Cell, TileMap};
+use crate::options::Options;
+use crate::save::SaveGame;
+use crate::scripting::Scripts;
+use crate::watcher::{Change, Watcher};
+use crate::{spawner, systems, validation};
+
+/// Width of the compass and map arrows, in pixels.
+const NEEDLE_WIDTH: f32 = 4.;
+/// Space between the movement buttons, in pixels.
+const BUTTON_MARGIN: f32 = 3.;
+/// Distance of the map floors behind the rest of the HUD, as a part of the
+/// HUD distance.
+const FLOOR_DEPTH: f32 = 0.01;
+
+/// What the HUD layer is built from, rebuilt only when it changes.
+#[derive(PartialEq)]
+struct HudFrame {
+    elements: Vec<Element>,
+    /// Camera position, yaw and pitch
+    camera: (Vec3, f32, f32),
+    size: (f32, f32),
+}
 
 pub struct App {
     map: TileMap<Arc<Model>>,
     scene: Scene,
     light_model: Arc<Model>,
+    phong_shader: Arc<Shader>,
+    wall_model: Arc<Model>,
+    floor_model: Arc<Model>,
     world: World,
     events: Vec<Event>,
+    settings: Settings,
+    rng: StdRng,
+    scripts: Scripts,
+    lighting: Lighting,
+    hud: Hud,
+    /// What the HUD layer was last built from
+    drawn_hud: Option<HudFrame>,
+    pointer: Pointer,
+    /// Actions clicked in the HUD, done on the next update
+    clicks: Vec<Action>,
+    gamepads: Gamepads,
+    watcher: Option<Watcher>,
 }
 
 impl Run for App {
     async fn create(gfx: &Gfx) -> Self {
+        let options = Options::get();
+        let settings = or_exit(Settings::load(options));
+
         let camera = Camera::perspective(
             (0., 0., 0.),
             gfx.width() as f32 / gfx.height() as f32,
-            (70. as f32).to_radians(),
-            0.1,
-            150.,
+            settings.camera.fov.to_radians(),
+            settings.camera.near,
+            settings.camera.far,
             (0. as f32).to_radians(),
             (0. as f32).to_radians(),
             Vec3::Y,
         );
 
-        let light = Light::new((0., 20., 0.), (1., 1., 0.9));
+        let light = Light::new((0., 0., 0.), (1., 1., 0.9));
 
         let phong_shader = Self::phong_shader(gfx).await;
         let solid_shader = Self::solid_shader(gfx).await;
         let wire_shader = Self::wire_shader(gfx).await;
 
         let mut scene = Scene::new(gfx, camera, light, &[wire_shader]).await;
-        scene.toggle_pass(crate::WIRE_PASS);
-
-        let material = MaterialBuilder::new("diffuse")
-            .diffuse_texture(crate::WALL_TEXTURE)
-            .await
-            .normal_texture(crate::WALL_TEXTURE_N)
-            .await
-            .build();
-
-        let wall_model = ModelBuilder::new()
-            .add_mesh(
-                Shapes::cube(3, 2, &[5, 5, 5, 5, 6, 4]),
-                Some(material.clone()),
-            )
-            .build(phong_shader.clone());
+        if !options.wireframe {
+            scene.toggle_pass(crate::WIRE_PASS);
+        }
 
-        let floor_model = ModelBuilder::new()
-            .add_mesh(Shapes::cube(3, 2, &[4]), Some(material))
-            .build(phong_shader);
+        let (wall_model, floor_model) = Self::tile_models(phong_shader.clone()).await;
 
         let mut map = TileMap::new();
 
-        map.load(crate::MAP, crate::TILE_SIZE, wall_model, floor_model)
-            .unwrap();
+        or_exit(map.load(
+            &or_exit(options.map_data()),
+            wall_model.clone(),
+            floor_model.clone(),
+        ));
+
+        Self::load_scene(&mut scene, &map, settings.map.tile_size);
 
-        Self::load_scene(&mut scene, &map);
+        let start = map.start_on(options.level as i32).unwrap_or(map.start);
 
-        scene.camera.position = map.start.into();
+        scene.camera.position = start * settings.map.tile_size;
 
         let light_model = scene
             .load_model(crate::LIGHT, None, solid_shader)
             .await
             .unwrap();
 
-        scene.add_node(
-            "light",
-            scene.light.position,
-            Quat::from_axis_angle(Vec3::Z, 0.),
-            Vec3::splat(1.),
-            light_model.clone(),
+        let mut world = World::new();
+
+        spawner::spawn_player(&mut world, start, &settings);
+        spawner::spawn_map_entities(&mut world, &map);
+
+        Self::load_lights(&mut scene, &world, &light_model, settings.map.tile_size);
+        Self::load_doors(
+            &mut scene,
+            &world,
+            &map,
+            &wall_model,
+            settings.map.tile_size,
         );
 
-        let mut world = World::new();
+        if let Some(path) = &options.load {
+            or_exit(SaveGame::read(path)).restore(&mut world);
+        }
 
-        world.spawn((
-            Name { name: "Bob".into() },
-            Player,
-            components::Camera::new(),
-            Position {
-                x: map.start.x,
-                y: map.start.y,
-                z: map.start.z,
-            },
-            Orientation::new(Facing::North),
-        ));
+        let scripts = Self::load_scripts(&map);
+        let lighting = Lighting::new(&map);
+        let mut hud = Hud::new(settings.hud.clone());
+        hud.load(&map);
+
+        let watcher = match options.map_path() {
+            Some(path) if options.watch => Watcher::new(&path)
+                .map_err(|e| error!("Cannot watch {}: {}", path.display(), e))
+                .ok(),
+            _ => None,
+        };
 
         App {
             map,
             scene,
             light_model,
+            phong_shader,
+            wall_model,
+            floor_model,
             world,
             events: Vec::new(),
+            hud,
+            drawn_hud: None,
+            pointer: Pointer::new(gfx.width() as f32, gfx.height() as f32),
+            clicks: Vec::new(),
+            gamepads: Gamepads::new(),
+            settings,
+            rng: StdRng::seed_from_u64(options.seed),
+            scripts,
+            lighting,
+            watcher,
         }
     }
 
     fn update(&mut self, delta: f32, gfx: &Gfx) {
+        self.hot_reload();
+        self.gamepads.poll(&mut self.events);
+
         systems::update(
             delta,
             &mut self.world,
-            &self.events,
+            &mut self.events,
             &self.map,
             &mut self.scene,
+            &self.settings,
+            &mut self.rng,
+            &mut self.scripts,
+            &mut self.lighting,
         );
 
-        let angular_speed = 10.;
-
-        let mut light_position: Vec3 = self.scene.light.position;
-        light_position =
-            (Quat::from_axis_angle((0., 0., 1.).into(), (angular_speed * delta).to_radians())
-                * light_position)
-                .into();
-
-        self.scene.light.update(light_position);
+        if self
+            .events
+            .iter()
+            .any(|e| matches!(e, Event::DoorChanged(_)))
+        {
+            self.scene.layer_mut("door").clear();
+            Self::load_doors(
+                &mut self.scene,
+                &self.world,
+                &self.map,
+                &self.wall_model,
+                self.settings.map.tile_size,
+            );
+        }
 
-        for node in self.scene.layer_mut("light").nodes_mut() {
-            if node.model().id == self.light_model.id {
-                node.move_to_position(light_position);
+        for event in &self.events {
+            if let Event::Message(message) = event {
+                info!("{}", message);
             }
         }
+        self.hud.update(&self.events, &self.settings.input);
+        self.save_keys();
+        self.draw_hud(gfx);
 
         self.scene.update(gfx);
 
         self.events.clear();
+
+        // Clicks end with the release of the mouse button, which also ends
+        // the free view: act on them separately
+        for action in self.clicks.drain(..) {
+            self.events.push(Event::Action(action));
+        }
     }
 
     fn render(&mut self, gfx: &Gfx) -> Result<(), RenderError> {
         self.scene.render(gfx)
     }
 
-    fn input(&mut self, _gfx: &Gfx, input: Input) {
+    fn input(&mut self, gfx: &Gfx, input: Input) {
+        let (width, height) = (gfx.width() as f32, gfx.height() as f32);
+
+        if let Some((x, y)) = self.pointer.input(&input, width, height) {
+            let view = View {
+                position: self.scene.camera.position / self.settings.map.tile_size,
+                yaw: self.scene.camera.yaw,
+                pitch: self.scene.camera.pitch,
+                fov: self.settings.camera.fov.to_radians(),
+            };
+            if let Some(action) = self
+                .hud
+                .click(&self.world, &self.map, &view, x, y, width, height)
+            {
+                self.clicks.push(action);
+            }
+        }
+
         self.events.push(Event::Input(input));
     }
 
@@ -154,7 +253,349 @@ impl Run for App {
 }
 
 impl App {
-    fn load_scene(scene: &mut Scene, map: &TileMap<Arc<Model>>) {
+    async fn tile_models(shader: Arc<Shader>) -> (Arc<Model>, Arc<Model>) {
+        let material = MaterialBuilder::new("diffuse")
+            .diffuse_texture(crate::WALL_TEXTURE)
+            .await
+            .normal_texture(crate::WALL_TEXTURE_N)
+            .await
+            .build();
+
+        let wall_model = ModelBuilder::new()
+            .add_mesh(
+                Shapes::cube(3, 2, &[5, 5, 5, 5, 6, 4]),
+                Some(material.clone()),
+            )
+            .build(shader.clone());
+
+        let floor_model = ModelBuilder::new()
+            .add_mesh(Shapes::cube(3, 2, &[4]), Some(material))
+            .build(shader);
+
+        (wall_model, floor_model)
+    }
+
+    /// Write the party to the save file with the save key, and restore it
+    /// with the load key.
+    fn save_keys(&mut self) {
+        let input = &self.settings.input;
+        let (mut save, mut load) = (false, false);
+
+        for event in &self.events {
+            if let Event::Input(Input::KeyPressed(key)) = event {
+                save |= input.is_key(key, &input.save_key);
+                load |= input.is_key(key, &input.load_key);
+            }
+        }
+
+        if !save && !load {
+            return;
+        }
+
+        let Some(path) = Options::get().save_path() else {
+            error!("No save file, use --save");
+            return;
+        };
+
+        if save {
+            match SaveGame::capture(&self.world).map(|game| game.write(&path)) {
+                Some(Ok(())) => info!("Saved to {}", path.display()),
+                Some(Err(e)) => error!("Cannot save: {}", e),
+                None => error!("Cannot save: no party"),
+            }
+        }
+
+        if load {
+            match SaveGame::read(&path) {
+                Ok(game) => {
+                    game.restore(&mut self.world);
+                    info!("Loaded {}", path.display());
+                }
+                Err(e) => error!("Cannot load: {}", e),
+            }
+        }
+    }
+
+    fn hot_reload(&mut self) {
+        let Some(watcher) = &self.watcher else {
+            return;
+        };
+
+        let mut reload = false;
+
+        for change in watcher.poll() {
+            match change {
+                Change::Map | Change::Script => reload = true,
+                Change::Asset(path) => match self.reload_asset(&path) {
+                    Ok(models) => reload |= models,
+                    Err(e) => error!("Cannot reload {}: {}", path.display(), e),
+                },
+            }
+        }
+
+        if reload {
+            self.reload_map();
+        }
+    }
+
+    /// Copy a modified asset next to the executable, as the build script
+    /// does, and rebuild the tile models if it is one of their textures.
+    /// Returns true if the map needs to be reloaded.
+    fn reload_asset(&mut self, path: &Path) -> Result<bool> {
+        let Some(name) = path.file_name() else {
+            return Ok(false);
+        };
+
+        fs::copy(path, crate::assets_dir().join(name))?;
+
+        if name == crate::WALL_TEXTURE || name == crate::WALL_TEXTURE_N {
+            info!("Reload materials");
+            (self.wall_model, self.floor_model) =
+                pollster::block_on(Self::tile_models(self.phong_shader.clone()));
+            return Ok(true);
+        }
+
+        Ok(false)
+    }
+
+    fn reload_map(&mut self) {
+        let data = match Options::get().map_data() {
+            Ok(data) => data,
+            Err(e) => {
+                error!("Cannot read map: {}", e);
+                return;
+            }
+        };
+
+        let mut map = TileMap::new();
+
+        if let Err(e) = map.load(&data, self.wall_model.clone(), self.floor_model.clone()) {
+            error!("Cannot load map: {}", e);
+            return;
+        }
+
+        for diagnostic in validation::validate(&map) {
+            warn!("{}", diagnostic);
+        }
+
+        let tile_size = self.settings.map.tile_size;
+
+        self.scene.layer_mut("wall").clear();
+        self.scene.layer_mut("floor").clear();
+        Self::load_scene(&mut self.scene, &map, tile_size);
+
+        spawner::despawn_map_entities(&mut self.world);
+        spawner::spawn_map_entities(&mut self.world, &map);
+
+        self.scene.layer_mut("light").clear();
+        Self::load_lights(&mut self.scene, &self.world, &self.light_model, tile_size);
+
+        for (_, (_, position)) in self.world.query_mut::<(&Player, &mut Position)>() {
+            if !map.is_walkable(map.cell((*position).into())) {
+                info!("Player position is no longer valid, move to start");
+                *position = Position::from(map.start);
+            }
+        }
+
+        self.scene.layer_mut("door").clear();
+        Self::load_doors(
+            &mut self.scene,
+            &self.world,
+            &map,
+            &self.wall_model,
+            tile_size,
+        );
+
+        self.scripts = Self::load_scripts(&map);
+        self.lighting = Lighting::new(&map);
+        self.hud.load(&map);
+
+        self.map = map;
+    }
+
+    fn load_scripts(map: &TileMap<Arc<Model>>) -> Scripts {
+        Scripts::load(map, &Options::get().script_dir()).unwrap_or_else(|e| {
+            error!("Cannot load scripts: {}", e);
+            Scripts::default()
+        })
+    }
+
+    /// Draw the HUD on a plane just in front of the camera, in the scene:
+    /// gobs has no overlay pass, so walls closer than the plane can hide it.
+    /// The HUD is made of shapes only, since gobs cannot render text.
+    fn draw_hud(&mut self, gfx: &Gfx) {
+        let (width, height) = (gfx.width() as f32, gfx.height() as f32);
+        if width == 0. || height == 0. {
+            return;
+        }
+
+        let camera = &self.scene.camera;
+        let frame = HudFrame {
+            elements: self.hud.elements(&self.world, &self.map, width, height),
+            camera: (camera.position, camera.yaw, camera.pitch),
+            size: (width, height),
+        };
+        if self.drawn_hud.as_ref() == Some(&frame) {
+            return;
+        }
+
+        let (yaw, pitch) = (camera.yaw, camera.pitch);
+        let forward = Vec3::new(
+            yaw.cos() * pitch.cos(),
+            pitch.sin(),
+            yaw.sin() * pitch.cos(),
+        );
+        let right = Vec3::new(-yaw.sin(), 0., yaw.cos());
+        let up = right.cross(forward);
+        let rotation = Quat::from_mat3(&Mat3::from_cols(right, up, -forward));
+
+        let distance = self.settings.camera.near * 2.;
+        let half_height = distance * (self.settings.camera.fov.to_radians() / 2.).tan();
+        let half_width = half_height * width / height;
+        let origin = camera.position;
+
+        // Screen rectangle, in pixels, to a flat box in the scene, further
+        // away by a part of the distance
+        let place = |rect: &Rect, depth: f32| {
+            let (half_width, half_height) = (half_width * (1. + depth), half_height * (1. + depth));
+            let x = ((rect.x + rect.width / 2.) / width * 2. - 1.) * half_width;
+            let y = (1. - (rect.y + rect.height / 2.) / height * 2.) * half_height;
+            let position = origin + forward * distance * (1. + depth) + right * x + up * y;
+            let scale = Vec3::new(
+                rect.width / width * 2. * half_width,
+                rect.height / height * 2. * half_height,
+                0.001,
+            );
+            (position, scale)
+        };
+
+        self.scene.layer_mut("hud").clear();
+
+        // Thin bar across a rectangle, turned clockwise from pointing up
+        let needle = |rect: &Rect, angle: f32| {
+            let needle = Rect::new(
+                rect.x + rect.width / 2. - NEEDLE_WIDTH / 2.,
+                rect.y,
+                NEEDLE_WIDTH,
+                rect.height,
+            );
+            (needle, Quat::from_axis_angle(forward, angle) * rotation)
+        };
+
+        for element in &frame.elements {
+            let rect = element.rect;
+            let mut model = &self.light_model;
+            let mut depth = 0.;
+
+            let (rect, rotation) = match element.widget {
+                Widget::Bar(fill) => (
+                    Rect::new(rect.x, rect.y, rect.width * fill, rect.height),
+                    rotation,
+                ),
+                Widget::Compass(facing) => needle(&rect, facing.yaw() + FRAC_PI_2),
+                Widget::Arrow(yaw) => needle(&rect, yaw + FRAC_PI_2),
+                Widget::Tile(MapTile::Wall) => {
+                    model = &self.wall_model;
+                    (rect, rotation)
+                }
+                Widget::Tile(MapTile::Floor) => {
+                    model = &self.floor_model;
+                    depth = FLOOR_DEPTH;
+                    (rect, rotation)
+                }
+                Widget::Tile(MapTile::Opening) => (
+                    Rect::new(
+                        rect.x + rect.width * 3. / 8.,
+                        rect.y + rect.height * 3. / 8.,
+                        rect.width / 4.,
+                        rect.height / 4.,
+                    ),
+                    rotation,
+                ),
+                Widget::Tile(MapTile::Door) => (
+                    Rect::new(
+                        rect.x + rect.width / 4.,
+                        rect.y + rect.height / 4.,
+                        rect.width / 2.,
+                        rect.height / 2.,
+                    ),
+                    rotation,
+                ),
+                Widget::Button(_) => (
+                    Rect::new(
+                        rect.x + BUTTON_MARGIN,
+                        rect.y + BUTTON_MARGIN,
+                        rect.width - BUTTON_MARGIN * 2.,
+                        rect.height - BUTTON_MARGIN * 2.,
+                    ),
+                    rotation,
+                ),
+            };
+
+            let (position, scale) = place(&rect, depth);
+            self.scene
+                .add_node("hud", position, rotation, scale, model.clone());
+        }
+
+        self.drawn_hud = Some(frame);
+    }
+
+    /// Show a marker on every light placed in the map.
+    fn load_lights(scene: &mut Scene, world: &World, model: &Arc<Model>, tile_size: f32) {
+        for (_, (_, position)) in world
+            .query::<(&LightSource, &Position)>()
+            .without::<&Player>()
+            .iter()
+        {
+            scene.add_node(
+                "light",
+                Into::<Vec3>::into(*position) * tile_size,
+                Quat::IDENTITY,
+                Vec3::splat(0.1 * tile_size),
+                model.clone(),
+            );
+        }
+    }
+
+    /// Show closed doors as thin walls across the passage, and secret doors
+    /// as plain walls.
+    fn load_doors(
+        scene: &mut Scene,
+        world: &World,
+        map: &TileMap<Arc<Model>>,
+        model: &Arc<Model>,
+        tile_size: f32,
+    ) {
+        for (_, (door, position, secret)) in
+            world.query::<(&Door, &Position, Option<&Secret>)>().iter()
+        {
+            if door.open {
+                continue;
+            }
+
+            let position: Vec3 = (*position).into();
+            let cell = map.cell(position);
+            let east = Cell::new(cell.x + 1, cell.y, cell.level);
+
+            let scale = if secret.is_some() {
+                Vec3::ONE
+            } else if map.is_wall(east) {
+                Vec3::new(1., 1., 0.2)
+            } else {
+                Vec3::new(0.2, 1., 1.)
+            };
+
+            scene.add_node(
+                "door",
+                position * tile_size,
+                Quat::IDENTITY,
+                scale * tile_size,
+                model.clone(),
+            );
+        }
+    }
+
+    fn load_scene(scene: &mut Scene, map: &TileMap<Arc<Model>>, tile_size: f32) {
         for tile in &map.tiles {
             let layer = match tile.tile {
                 crate::map::TileSet::WALL(_) => "wall",
@@ -163,9 +604,9 @@ impl App {
 
             scene.add_node(
                 layer,
-                tile.position,
+                tile.position * tile_size,
                 Quat::IDENTITY,
-                Vec3::ONE,
+                Vec3::splat(tile_size),
                 tile.tile.model(),
             );
         }
@@ -210,3 +651,11 @@ impl App {
         .await
     }
 }
+
+/// Stop on an error in the files given by the user rather than panicking.
+fn or_exit<T>(result: Result<T>) -> T {
+    result.unwrap_or_else(|e| {
+        error!("{}", e);
+        process::exit(1)
+    })
+}
@@ -1,17 +1,43 @@
 mod animation;
 mod camera;
+mod door;
+mod free_movement;
+mod hazard;
 mod health;
 mod intent;
+mod inventory;
+mod light;
+mod magic;
+mod monster;
 mod name;
 mod orientation;
 mod player;
 mod position;
+mod projectile;
+mod skills;
+mod status;
+mod sticks;
+mod trap;
+mod trigger;
 
 pub use animation::{Animation, AnimationType};
 pub use camera::Camera;
+pub use door::{Door, Secret};
+pub use free_movement::FreeMovement;
+pub use hazard::{AntiMagic, Hazard, HazardKind};
 pub use health::Health;
 pub use intent::{Action, Intent};
+pub use inventory::{Inventory, Item};
+pub use light::{Flicker, Fuel, LightLevel, LightSource};
+pub use magic::{Conjured, Mana, Spellbook, Target};
+pub use monster::{Aware, Monster};
 pub use name::Name;
 pub use orientation::Orientation;
 pub use player::Player;
 pub use position::Position;
+pub use projectile::{Projectile, ProjectileKind};
+pub use skills::Skills;
+pub use status::{Duration, StatusEffect, StatusEffects, StatusKind};
+pub use sticks::Sticks;
+pub use trap::{Trap, TrapKind};
+pub use trigger::{DoorOperation, Trigger, TriggerAction, TriggerKind};
@@ -1,3 +1,5 @@
+use glam::Vec3;
+
 use crate::movement::Direction;
 
 use super::{Orientation, Position};
@@ -6,6 +8,8 @@ use super::{Orientation, Position};
 pub enum AnimationType {
     TRANSLATE(Position, Direction),
     ROTATE(Orientation, Direction),
+    /// Blocked move, toward the obstacle and back
+    BUMP(Vec3),
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -16,11 +20,11 @@ pub struct Animation {
 }
 
 impl Animation {
-    pub fn new(effect: AnimationType) -> Self {
+    pub fn new(effect: AnimationType, frames: u32) -> Self {
         Animation {
             effect,
             progress: 0,
-            frames: 30,
+            frames,
         }
     }
 
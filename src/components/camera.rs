@@ -1,8 +1,18 @@
+use glam::Vec3;
+
 #[derive(Debug)]
 pub struct Camera {
     pub free_view: bool,
     pub pitch: f32,
     pub yaw: f32,
+    /// Strength of the current shake, from 0 to 1
+    pub shake: f32,
+    /// Time driving the shake and head-bob, in seconds
+    pub time: f32,
+    /// Offsets from the effects, applied to the view only
+    pub offset: Vec3,
+    pub yaw_offset: f32,
+    pub pitch_offset: f32,
 }
 
 impl Camera {
@@ -11,6 +21,15 @@ impl Camera {
             free_view: false,
             pitch: 0.,
             yaw: 0.,
+            shake: 0.,
+            time: 0.,
+            offset: Vec3::ZERO,
+            yaw_offset: 0.,
+            pitch_offset: 0.,
         }
     }
+
+    pub fn shake(&mut self, amount: f32) {
+        self.shake = (self.shake + amount).min(1.);
+    }
 }
@@ -0,0 +1,13 @@
+/// Door placed on a `+` cell of the map. Closed doors block movement,
+/// locked ones can only be operated by triggers.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Door {
+    pub open: bool,
+    pub locked: bool,
+}
+
+/// Set on secret doors until they are found by searching.
+#[derive(Clone, Copy, Debug)]
+pub struct Secret {
+    pub difficulty: u32,
+}
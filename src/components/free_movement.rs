@@ -0,0 +1,8 @@
+/// Continuous movement instead of steps from cell to cell. The axes hold
+/// the movement keys currently pressed, from -1 to 1.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FreeMovement {
+    pub forward: f32,
+    pub strafe: f32,
+    pub turn: f32,
+}
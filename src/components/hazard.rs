@@ -0,0 +1,22 @@
+use glam::Vec3;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HazardKind {
+    /// Turns the party to a random direction without notice
+    Spinner,
+    /// One-way teleporter
+    Teleporter(Vec3),
+    /// Drops the party to the level below
+    Pit,
+    /// Prevents spell casting
+    AntiMagic,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Hazard {
+    pub kind: HazardKind,
+}
+
+/// Set on entities standing in an anti-magic zone.
+#[derive(Clone, Copy, Debug)]
+pub struct AntiMagic;
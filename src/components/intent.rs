@@ -1,3 +1,4 @@
+use crate::components::{ProjectileKind, Target};
 use crate::movement::Direction;
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -7,6 +8,35 @@ pub enum Action {
     Turn(Direction),
     Look((f32, f32)),
     ControlCamera(bool),
+    /// Switch between grid steps and free movement
+    ToggleFreeMovement,
+    Disarm,
+    Interact,
+    /// Pick up the items lying in the cell ahead
+    PickUp,
+    Search,
+    /// Cast a spell of the spellbook
+    Cast(usize, Target),
+    /// Throw or shoot an item of the inventory
+    Throw(ProjectileKind),
+}
+
+impl Action {
+    /// Whether the action is done in the world, rather than moving the view
+    /// or changing how the party moves.
+    pub fn is_deed(&self) -> bool {
+        matches!(
+            self,
+            Action::Move(_)
+                | Action::Turn(_)
+                | Action::Disarm
+                | Action::Interact
+                | Action::PickUp
+                | Action::Search
+                | Action::Cast(..)
+                | Action::Throw(_)
+        )
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
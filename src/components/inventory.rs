@@ -0,0 +1,30 @@
+#[derive(Clone, Debug, Default)]
+pub struct Inventory {
+    pub items: Vec<String>,
+}
+
+impl Inventory {
+    pub fn has(&self, item: &str) -> bool {
+        self.items.iter().any(|i| i == item)
+    }
+
+    pub fn add(&mut self, item: String) {
+        self.items.push(item);
+    }
+
+    pub fn remove(&mut self, item: &str) -> bool {
+        match self.items.iter().position(|i| i == item) {
+            Some(index) => {
+                self.items.remove(index);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Item lying on the floor, picked up by walking on its cell.
+#[derive(Clone, Debug)]
+pub struct Item {
+    pub name: String,
+}
@@ -0,0 +1,109 @@
+use std::str::FromStr;
+
+use anyhow::{anyhow, Result};
+use glam::Vec3;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Flicker {
+    Steady,
+    Torch,
+    Pulse,
+}
+
+impl Flicker {
+    /// Intensity factor at a given time.
+    pub fn factor(&self, time: f32) -> f32 {
+        match self {
+            Flicker::Steady => 1.,
+            Flicker::Torch => {
+                let noise =
+                    (time * 7.).sin() * 0.5 + (time * 13.).sin() * 0.3 + (time * 29.).sin() * 0.2;
+                0.85 + 0.15 * noise
+            }
+            Flicker::Pulse => 0.75 + 0.25 * (time * 2.).sin(),
+        }
+    }
+}
+
+impl FromStr for Flicker {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "steady" => Ok(Flicker::Steady),
+            "torch" => Ok(Flicker::Torch),
+            "pulse" => Ok(Flicker::Pulse),
+            _ => Err(anyhow!("Unknown flicker {}", s)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct LightSource {
+    pub colour: Vec3,
+    pub radius: f32,
+    pub flicker: Flicker,
+    pub intensity: f32,
+    pub time: f32,
+}
+
+impl LightSource {
+    pub fn new(colour: Vec3, radius: f32, flicker: Flicker) -> Self {
+        LightSource {
+            colour,
+            radius,
+            flicker,
+            intensity: 1.,
+            time: 0.,
+        }
+    }
+
+    pub fn torch() -> Self {
+        Self::new(Vec3::new(1., 0.6, 0.25), 4., Flicker::Torch)
+    }
+
+    pub fn crystal() -> Self {
+        Self::new(Vec3::new(0.4, 0.6, 1.), 3., Flicker::Pulse)
+    }
+
+    /// Light received at a distance, fading linearly to 0 at the radius.
+    pub fn brightness(&self, distance: f32) -> f32 {
+        if self.radius <= 0. {
+            return 0.;
+        }
+
+        self.intensity * (1. - distance / self.radius).max(0.)
+    }
+}
+
+/// Light level of the cell an entity stands on, updated every frame.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LightLevel {
+    pub level: f32,
+}
+
+/// Remaining burn time of a carried light, in seconds.
+#[derive(Clone, Copy, Debug)]
+pub struct Fuel {
+    pub remaining: f32,
+    pub duration: f32,
+}
+
+impl Fuel {
+    pub fn new(duration: f32) -> Self {
+        Fuel {
+            remaining: duration,
+            duration,
+        }
+    }
+
+    /// Strength of the light: full until the last fifth of the fuel, then
+    /// fading out.
+    pub fn strength(&self) -> f32 {
+        if self.duration <= 0. {
+            return 0.;
+        }
+
+        (5. * self.remaining / self.duration).clamp(0., 1.)
+    }
+}
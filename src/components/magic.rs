@@ -0,0 +1,67 @@
+use crate::map::Cell;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Mana {
+    pub current: u32,
+    pub max: u32,
+    /// Fraction of a point recovered so far
+    pub recovery: f32,
+}
+
+impl Mana {
+    pub fn new(max: u32) -> Self {
+        Mana {
+            current: max,
+            max,
+            recovery: 0.,
+        }
+    }
+
+    /// Spend mana if there is enough of it.
+    pub fn spend(&mut self, amount: u32) -> bool {
+        if self.current < amount {
+            return false;
+        }
+        self.current -= amount;
+        true
+    }
+
+    pub fn recover(&mut self, amount: f32) {
+        if self.current >= self.max {
+            self.recovery = 0.;
+            return;
+        }
+
+        self.recovery += amount;
+        let points = self.recovery.floor();
+        self.recovery -= points;
+        self.current = (self.current + points as u32).min(self.max);
+    }
+}
+
+/// Spells an entity can cast, with the time left before each can be cast
+/// again.
+#[derive(Clone, Debug, Default)]
+pub struct Spellbook {
+    pub spells: Vec<String>,
+    pub cooldowns: Vec<f32>,
+}
+
+impl Spellbook {
+    pub fn new(spells: Vec<String>) -> Self {
+        let cooldowns = vec![0.; spells.len()];
+
+        Spellbook { spells, cooldowns }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Target {
+    /// First obstacle or creature ahead of the caster, within range
+    Ahead,
+    Cell(Cell),
+}
+
+/// Set on entities created by spells, removed once their light is spent.
+#[derive(Clone, Copy, Debug)]
+pub struct Conjured;
@@ -0,0 +1,6 @@
+#[derive(Clone, Copy, Debug)]
+pub struct Monster;
+
+/// The monster has noticed the party.
+#[derive(Clone, Copy, Debug)]
+pub struct Aware;
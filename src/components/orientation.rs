@@ -1,4 +1,4 @@
-use std::f32::consts::FRAC_PI_2;
+use std::f32::consts::FRAC_PI_4;
 
 use crate::movement::{Direction, Facing};
 
@@ -6,6 +6,9 @@ use crate::movement::{Direction, Facing};
 pub struct Orientation {
     pub facing: Facing,
     pub yaw: f32,
+    /// Size of a turn in eighths of a full turn: 2 for the four cardinal
+    /// directions, 1 to also face diagonals
+    pub turn: i32,
 }
 
 impl Orientation {
@@ -13,26 +16,36 @@ impl Orientation {
         Orientation {
             facing,
             yaw: facing.yaw(),
+            turn: 2,
         }
     }
 
+    /// Turn by eighths of a turn rather than quarters.
+    pub fn with_diagonals(mut self, diagonals: bool) -> Self {
+        self.turn = if diagonals { 1 } else { 2 };
+        self
+    }
+
+    /// Facings that can be turned to, clockwise from North.
+    pub fn facings(&self) -> Vec<Facing> {
+        (0..8)
+            .step_by(self.turn as usize)
+            .map(|eighths| Facing::North.rotate(eighths))
+            .collect()
+    }
+
     pub fn face(&mut self, facing: Facing) {
         self.facing = facing;
         self.yaw = facing.yaw()
     }
 
     pub fn rotate(&mut self, direction: Direction, amount: f32, clip: bool) {
-        let amount = amount * FRAC_PI_2;
+        let eighths = direction.eighths(self.turn);
         if clip {
-            self.facing = self.facing.turn(direction);
+            self.facing = self.facing.rotate(eighths);
             self.yaw = self.facing.yaw();
         } else {
-            match direction {
-                Direction::Left => self.yaw -= amount,
-                Direction::Right => self.yaw += amount,
-                Direction::Forward => (),
-                Direction::Backward => self.yaw += amount,
-            }
+            self.yaw += eighths as f32 * FRAC_PI_4 * amount;
         }
     }
 }
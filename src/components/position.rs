@@ -21,6 +21,16 @@ impl Position {
     }
 }
 
+impl From<Vec3> for Position {
+    fn from(position: Vec3) -> Self {
+        Position {
+            x: position.x,
+            y: position.y,
+            z: position.z,
+        }
+    }
+}
+
 impl Into<Vec3> for Position {
     fn into(self) -> Vec3 {
         Vec3::new(self.x, self.y, self.z)
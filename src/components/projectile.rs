@@ -0,0 +1,48 @@
+use hecs::Entity;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProjectileKind {
+    Arrow,
+    Dagger,
+    Fireball,
+}
+
+impl ProjectileKind {
+    pub fn damage(&self) -> u32 {
+        match self {
+            ProjectileKind::Arrow => 4,
+            ProjectileKind::Dagger => 3,
+            ProjectileKind::Fireball => 6,
+        }
+    }
+
+    /// Number of cells travelled before landing.
+    pub fn range(&self) -> u32 {
+        match self {
+            ProjectileKind::Arrow => 8,
+            ProjectileKind::Dagger => 5,
+            ProjectileKind::Fireball => 8,
+        }
+    }
+
+    /// Item left on the floor once the projectile landed.
+    pub fn item(&self) -> Option<&'static str> {
+        match self {
+            ProjectileKind::Arrow => Some("arrow"),
+            ProjectileKind::Dagger => Some("dagger"),
+            ProjectileKind::Fireball => None,
+        }
+    }
+}
+
+/// Flying object moving one cell at a time ahead of it.
+#[derive(Clone, Copy, Debug)]
+pub struct Projectile {
+    pub kind: ProjectileKind,
+    /// Entity that threw or cast it
+    pub owner: Entity,
+    /// Cells left before it lands
+    pub range: u32,
+}
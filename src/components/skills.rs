@@ -0,0 +1,14 @@
+use rand::Rng;
+
+/// Bonuses added to a d20 roll for skill checks.
+#[derive(Clone, Copy, Debug)]
+pub struct Skills {
+    pub perception: i32,
+    pub disarm: i32,
+}
+
+impl Skills {
+    pub fn check<R: Rng>(rng: &mut R, skill: i32, difficulty: u32) -> bool {
+        rng.gen_range(1..=20) + skill >= difficulty as i32
+    }
+}
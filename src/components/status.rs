@@ -0,0 +1,114 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StatusKind {
+    /// Loses health every second
+    Poison,
+    /// Cannot act
+    Paralysis,
+    /// Moves and turns twice as fast
+    Haste,
+    /// Sees nothing and cannot search
+    Blindness,
+    /// Floats over pits
+    Levitation,
+    /// Floats over pits and moves up and down through openings
+    Flying,
+    /// Recovers health every second
+    Regeneration,
+}
+
+impl StatusKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            StatusKind::Poison => "poisoned",
+            StatusKind::Paralysis => "paralysed",
+            StatusKind::Haste => "hasted",
+            StatusKind::Blindness => "blind",
+            StatusKind::Levitation => "levitating",
+            StatusKind::Flying => "flying",
+            StatusKind::Regeneration => "regenerating",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Duration {
+    Seconds(f32),
+    /// Actions completed by the player
+    Turns(u32),
+}
+
+impl Duration {
+    pub fn expired(&self) -> bool {
+        match self {
+            Duration::Seconds(seconds) => *seconds <= 0.,
+            Duration::Turns(turns) => *turns == 0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub struct StatusEffect {
+    pub kind: StatusKind,
+    pub duration: Duration,
+    /// Health lost or recovered per second by poison and regeneration
+    #[serde(default = "default_strength")]
+    pub strength: u32,
+    /// Time since the last tick, in seconds
+    #[serde(skip)]
+    pub timer: f32,
+}
+
+fn default_strength() -> u32 {
+    1
+}
+
+impl StatusEffect {
+    pub fn new(kind: StatusKind, duration: Duration, strength: u32) -> Self {
+        StatusEffect {
+            kind,
+            duration,
+            strength,
+            timer: 0.,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct StatusEffects {
+    pub effects: Vec<StatusEffect>,
+}
+
+impl StatusEffects {
+    pub fn has(&self, kind: StatusKind) -> bool {
+        self.effects.iter().any(|e| e.kind == kind)
+    }
+
+    /// Add an effect. Poisons stack their strength, other effects of the
+    /// same kind are refreshed with the longest duration and strongest
+    /// strength.
+    pub fn add(&mut self, effect: StatusEffect) {
+        let Some(existing) = self.effects.iter_mut().find(|e| e.kind == effect.kind) else {
+            self.effects.push(effect);
+            return;
+        };
+
+        existing.strength = match effect.kind {
+            StatusKind::Poison => existing.strength + effect.strength,
+            _ => existing.strength.max(effect.strength),
+        };
+
+        existing.duration = match (existing.duration, effect.duration) {
+            (Duration::Seconds(a), Duration::Seconds(b)) => Duration::Seconds(a.max(b)),
+            (Duration::Turns(a), Duration::Turns(b)) => Duration::Turns(a.max(b)),
+            (_, duration) => duration,
+        };
+    }
+
+    pub fn remove(&mut self, kind: StatusKind) {
+        self.effects.retain(|e| e.kind != kind);
+    }
+}
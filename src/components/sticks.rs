@@ -0,0 +1,10 @@
+use glam::Vec2;
+
+/// Gamepad sticks positions, from -1 to 1, as last reported.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Sticks {
+    pub left: Vec2,
+    pub right: Vec2,
+    /// The right stick is controlling the view
+    pub looking: bool,
+}
@@ -0,0 +1,28 @@
+use glam::Vec3;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TrapKind {
+    Pit,
+    Spikes,
+    Darts,
+    Teleport(Vec3),
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Trap {
+    pub kind: TrapKind,
+    pub hidden: bool,
+    pub armed: bool,
+    pub difficulty: u32,
+}
+
+impl Trap {
+    pub fn damage(&self) -> u32 {
+        match self.kind {
+            TrapKind::Pit => 6,
+            TrapKind::Spikes => 4,
+            TrapKind::Darts => 3,
+            TrapKind::Teleport(_) => 0,
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use glam::Vec3;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TriggerKind {
+    /// Wall lever, switched on and off by interacting with it
+    Lever,
+    /// Wall button, activated each time it is pushed
+    Button,
+    /// Floor plate, pressed while something stands on it
+    Plate,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DoorOperation {
+    Open,
+    Close,
+    Toggle,
+}
+
+#[derive(Clone, Debug)]
+pub enum TriggerAction {
+    Door(Vec3, DoorOperation),
+    Spawn(Vec3, String, u32),
+    Message(String),
+}
+
+#[derive(Clone, Debug)]
+pub struct Trigger {
+    pub kind: TriggerKind,
+    /// Item the activating entity must carry
+    pub requires: Option<String>,
+    /// Triggers of a group only fire when all of them are active
+    pub group: Option<String>,
+    pub once: bool,
+    pub fired: bool,
+    /// Lever switched on, plate pressed or button pushed
+    pub active: bool,
+    pub actions: Vec<TriggerAction>,
+}
+
+impl Trigger {
+    pub fn new(kind: TriggerKind) -> Self {
+        Trigger {
+            kind,
+            requires: None,
+            group: None,
+            once: false,
+            fired: false,
+            active: false,
+            actions: Vec::new(),
+        }
+    }
+}
@@ -0,0 +1,403 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+use gobs::game::input::Key;
+use serde::{Deserialize, Serialize};
+use toml::{Table, Value};
+
+use crate::hud::Layout;
+use crate::options::Options;
+use crate::spells::{self, Spell};
+
+const CONFIG_FILE: &str = "blobber/settings.toml";
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct CameraSettings {
+    /// Vertical field of view, in degrees
+    pub fov: f32,
+    pub near: f32,
+    pub far: f32,
+    /// Highest angle the free view can look up or down, in degrees
+    pub max_pitch: f32,
+    /// Rate at which the view returns to the party facing when free view
+    /// ends, per second
+    pub recenter_speed: f32,
+}
+
+impl Default for CameraSettings {
+    fn default() -> Self {
+        CameraSettings {
+            fov: 70.,
+            near: 0.1,
+            far: 150.,
+            max_pitch: 80.,
+            recenter_speed: 8.,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct EffectSettings {
+    /// Camera motion effects, turned off for players sensitive to motion
+    pub enabled: bool,
+    /// Height of the head-bob while stepping, in tiles
+    pub head_bob: f32,
+    /// Distance the view moves toward a wall when bumping into it, in tiles
+    pub bump: f32,
+    /// Shake added when walking into a wall, from 0 to 1
+    pub bump_shake: f32,
+    /// Shake added per point of damage taken, from 0 to 1
+    pub damage_shake: f32,
+    /// Camera offset at full shake, in tiles
+    pub shake_offset: f32,
+    /// Camera rotation at full shake, in degrees
+    pub shake_angle: f32,
+    /// Shake lost per second
+    pub shake_decay: f32,
+}
+
+impl Default for EffectSettings {
+    fn default() -> Self {
+        EffectSettings {
+            enabled: true,
+            head_bob: 0.04,
+            bump: 0.1,
+            bump_shake: 0.3,
+            damage_shake: 0.1,
+            shake_offset: 0.05,
+            shake_angle: 2.,
+            shake_decay: 1.5,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct MapSettings {
+    /// Size of a tile in the scene
+    pub tile_size: f32,
+}
+
+impl Default for MapSettings {
+    fn default() -> Self {
+        MapSettings { tile_size: 1. }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AnimationSettings {
+    /// Number of frames of a move or turn animation
+    pub frames: u32,
+}
+
+impl Default for AnimationSettings {
+    fn default() -> Self {
+        AnimationSettings { frames: 30 }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LightSettings {
+    /// Burn time of the party torch, in seconds
+    pub torch_duration: f32,
+    /// Radius of the party torch, in tiles
+    pub torch_radius: f32,
+}
+
+impl Default for LightSettings {
+    fn default() -> Self {
+        LightSettings {
+            torch_duration: 600.,
+            torch_radius: 4.,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct InputSettings {
+    /// Scaling applied to horizontal mouse motion in free view
+    pub sensitivity_x: f32,
+    /// Scaling applied to vertical mouse motion in free view
+    pub sensitivity_y: f32,
+    pub invert_x: bool,
+    pub invert_y: bool,
+    /// Part of the gamepad sticks travel ignored around the center, from 0
+    /// to 1
+    pub dead_zone: f32,
+    /// Scaling applied to the right stick in free view
+    pub stick_speed: f32,
+    /// Keys casting the spells of the spellbook, in order
+    pub spell_keys: Vec<String>,
+    /// Key switching between the minimap and the whole level
+    pub map_key: String,
+    /// Key showing fewer cells in the minimap
+    pub zoom_in_key: String,
+    /// Key showing more cells in the minimap
+    pub zoom_out_key: String,
+    /// Key saving the party to the save file
+    pub save_key: String,
+    /// Key restoring the party from the save file
+    pub load_key: String,
+}
+
+impl Default for InputSettings {
+    fn default() -> Self {
+        InputSettings {
+            sensitivity_x: 1.,
+            sensitivity_y: 1.,
+            invert_x: false,
+            invert_y: false,
+            dead_zone: 0.2,
+            stick_speed: 3.,
+            spell_keys: [
+                "C", "V", "B", "N", "F", "G", "H", "J", "K", "L", "M", "W", "U",
+            ]
+            .map(String::from)
+            .to_vec(),
+            map_key: "Tab".into(),
+            zoom_in_key: "I".into(),
+            zoom_out_key: "O".into(),
+            save_key: "Return".into(),
+            load_key: "Escape".into(),
+        }
+    }
+}
+
+impl InputSettings {
+    /// View rotation for a mouse motion.
+    pub fn look(&self, dx: f32, dy: f32, delta: f32) -> (f32, f32) {
+        let sign = |invert: bool| if invert { -1. } else { 1. };
+
+        (
+            dx * self.sensitivity_x * sign(self.invert_x) * delta,
+            dy * self.sensitivity_y * sign(self.invert_y) * delta,
+        )
+    }
+
+    /// Spellbook slot cast with a key.
+    pub fn spell_slot(&self, key: &Key) -> Option<usize> {
+        let name = key_name(key)?;
+        self.spell_keys.iter().position(|k| k == name)
+    }
+
+    /// Whether a key is the one named in a setting.
+    pub fn is_key(&self, key: &Key, setting: &str) -> bool {
+        key_name(key) == Some(setting)
+    }
+
+    /// Whether a key is bound to the map or to saving rather than to the
+    /// party.
+    pub fn is_app_key(&self, key: &Key) -> bool {
+        [
+            &self.map_key,
+            &self.zoom_in_key,
+            &self.zoom_out_key,
+            &self.save_key,
+            &self.load_key,
+        ]
+        .into_iter()
+        .any(|setting| self.is_key(key, setting))
+    }
+
+    /// Stick position with the dead zone removed, the rest of its travel
+    /// scaled back to the full range.
+    pub fn stick(&self, x: f32, y: f32) -> (f32, f32) {
+        let length = x.hypot(y);
+        if length <= self.dead_zone {
+            return (0., 0.);
+        }
+
+        let scale = ((length - self.dead_zone) / (1. - self.dead_zone)).min(1.) / length;
+        (x * scale, y * scale)
+    }
+}
+
+/// Name of a key in the settings.
+fn key_name(key: &Key) -> Option<&'static str> {
+    let name = match key {
+        Key::A => "A",
+        Key::B => "B",
+        Key::C => "C",
+        Key::D => "D",
+        Key::E => "E",
+        Key::F => "F",
+        Key::G => "G",
+        Key::H => "H",
+        Key::I => "I",
+        Key::J => "J",
+        Key::K => "K",
+        Key::L => "L",
+        Key::M => "M",
+        Key::N => "N",
+        Key::O => "O",
+        Key::P => "P",
+        Key::Q => "Q",
+        Key::R => "R",
+        Key::S => "S",
+        Key::T => "T",
+        Key::U => "U",
+        Key::V => "V",
+        Key::W => "W",
+        Key::X => "X",
+        Key::Y => "Y",
+        Key::Z => "Z",
+        Key::Up => "Up",
+        Key::Down => "Down",
+        Key::Left => "Left",
+        Key::Right => "Right",
+        Key::Space => "Space",
+        Key::Return => "Return",
+        Key::Escape => "Escape",
+        Key::Tab => "Tab",
+        Key::LShift => "LShift",
+        _ => return None,
+    };
+
+    Some(name)
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct MovementSettings {
+    /// Turn by eighths of a turn to face and move along diagonals
+    pub diagonal: bool,
+    /// Allow diagonal moves past a single blocked corner
+    pub cut_corners: bool,
+    /// Start in free movement rather than moving from cell to cell
+    pub free: bool,
+    /// Free movement speed, in tiles per second
+    pub speed: f32,
+    /// Free movement turn speed, in degrees per second
+    pub turn_speed: f32,
+    /// Radius of the party when colliding with walls in free movement, in
+    /// tiles
+    pub radius: f32,
+}
+
+impl Default for MovementSettings {
+    fn default() -> Self {
+        MovementSettings {
+            diagonal: false,
+            cut_corners: false,
+            free: false,
+            speed: 2.,
+            turn_speed: 120.,
+            radius: 0.25,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct MagicSettings {
+    /// Mana points recovered per second
+    pub mana_regen: f32,
+    pub spells: Vec<Spell>,
+}
+
+impl Default for MagicSettings {
+    fn default() -> Self {
+        MagicSettings {
+            mana_regen: 0.2,
+            spells: spells::builtin(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Settings {
+    pub camera: CameraSettings,
+    pub effects: EffectSettings,
+    pub map: MapSettings,
+    pub animation: AnimationSettings,
+    pub light: LightSettings,
+    pub input: InputSettings,
+    pub movement: MovementSettings,
+    pub magic: MagicSettings,
+    pub hud: Layout,
+}
+
+impl Settings {
+    /// Build the settings from the defaults, then the user file (given on
+    /// the command line or from the user config directory), then the
+    /// `--set key=value` overrides.
+    pub fn load(options: &Options) -> Result<Self> {
+        let mut settings = Value::try_from(Settings::default())?;
+
+        if let Some(path) = options.config.clone().or_else(Self::user_file) {
+            let data =
+                fs::read_to_string(&path).map_err(|e| anyhow!("{}: {}", path.display(), e))?;
+            let table = data
+                .parse::<Table>()
+                .map_err(|e| anyhow!("{}: {}", path.display(), e))?;
+            merge(&mut settings, Value::Table(table));
+        }
+
+        for option in &options.set {
+            let (key, value) = option
+                .split_once('=')
+                .ok_or_else(|| anyhow!("Invalid setting {}, expected key=value", option))?;
+            set(&mut settings, key.trim(), parse_value(value.trim()))?;
+        }
+
+        Ok(settings.try_into()?)
+    }
+
+    fn user_file() -> Option<PathBuf> {
+        let dir = env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+
+        let path = dir.join(CONFIG_FILE);
+
+        path.exists().then_some(path)
+    }
+}
+
+fn merge(base: &mut Value, layer: Value) {
+    match (base, layer) {
+        (Value::Table(base), Value::Table(layer)) => {
+            for (key, value) in layer {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, layer) => *base = layer,
+    }
+}
+
+fn set(settings: &mut Value, key: &str, value: Value) -> Result<()> {
+    let mut current = settings;
+
+    for part in key.split('.') {
+        current = current
+            .as_table_mut()
+            .and_then(|table| table.get_mut(part))
+            .ok_or_else(|| anyhow!("Unknown setting {}", key))?;
+    }
+
+    *current = value;
+
+    Ok(())
+}
+
+/// Parse a value as TOML, falling back to a plain string.
+fn parse_value(value: &str) -> Value {
+    format!("value = {}", value)
+        .parse::<Table>()
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| Value::String(value.to_string()))
+}
@@ -1,6 +1,40 @@
 use gobs::game::input::Input;
+use hecs::Entity;
+
+use crate::components::Action;
+use crate::gamepad::GamepadInput;
+use crate::map::Cell;
 
 #[derive(Debug)]
 pub enum Event {
     Input(Input),
+    Gamepad(GamepadInput),
+    /// Action chosen through the UI rather than bound to an input.
+    Action(Action),
+    /// An entity finished moving to a new cell.
+    Moved(Entity),
+    Damaged(Entity, u32),
+    /// An entity tried to move into a wall, a closed door or a cell it
+    /// cannot climb to.
+    Collided {
+        entity: Entity,
+        cell: Cell,
+    },
+    Message(String),
+    /// A trigger was activated by an entity.
+    Triggered {
+        trigger: Entity,
+        by: Entity,
+    },
+    /// A door was opened or closed.
+    DoorChanged(Entity),
+    /// An entity interacted with the cell in front of it, `handled` is false
+    /// if there was nothing to interact with.
+    Interacted {
+        by: Entity,
+        cell: Cell,
+        handled: bool,
+    },
+    /// The player completed an action.
+    Turn,
 }
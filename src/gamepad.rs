@@ -0,0 +1,115 @@
+use gilrs::{EventType, Gilrs};
+use log::*;
+
+use crate::events::Event;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Button {
+    Up,
+    Down,
+    Left,
+    Right,
+    South,
+    East,
+    West,
+    North,
+    LeftShoulder,
+    RightShoulder,
+    Select,
+    Start,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Axis {
+    LeftX,
+    LeftY,
+    RightX,
+    RightY,
+}
+
+/// Gamepad input, not reported by the window events.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GamepadInput {
+    Pressed(Button),
+    Released(Button),
+    /// Stick position from -1 to 1, up and right being positive.
+    Axis(Axis, f32),
+}
+
+/// Connected gamepads. Without gamepad support on the system, nothing is
+/// reported.
+pub struct Gamepads {
+    gilrs: Option<Gilrs>,
+}
+
+impl Gamepads {
+    pub fn new() -> Self {
+        let gilrs = Gilrs::new()
+            .map_err(|e| warn!("No gamepad support: {}", e))
+            .ok();
+
+        Gamepads { gilrs }
+    }
+
+    /// Add the gamepad input received since the last poll to the events.
+    pub fn poll(&mut self, events: &mut Vec<Event>) {
+        let Some(gilrs) = &mut self.gilrs else {
+            return;
+        };
+
+        while let Some(event) = gilrs.next_event() {
+            let input = match event.event {
+                EventType::ButtonPressed(button, _) => button_of(button).map(GamepadInput::Pressed),
+                EventType::ButtonReleased(button, _) => {
+                    button_of(button).map(GamepadInput::Released)
+                }
+                EventType::AxisChanged(axis, value, _) => {
+                    axis_of(axis).map(|axis| GamepadInput::Axis(axis, value))
+                }
+                EventType::Connected => {
+                    info!("Gamepad connected: {}", gilrs.gamepad(event.id).name());
+                    None
+                }
+                _ => None,
+            };
+
+            if let Some(input) = input {
+                events.push(Event::Gamepad(input));
+            }
+        }
+    }
+}
+
+impl Default for Gamepads {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn button_of(button: gilrs::Button) -> Option<Button> {
+    match button {
+        gilrs::Button::DPadUp => Some(Button::Up),
+        gilrs::Button::DPadDown => Some(Button::Down),
+        gilrs::Button::DPadLeft => Some(Button::Left),
+        gilrs::Button::DPadRight => Some(Button::Right),
+        gilrs::Button::South => Some(Button::South),
+        gilrs::Button::East => Some(Button::East),
+        gilrs::Button::West => Some(Button::West),
+        gilrs::Button::North => Some(Button::North),
+        gilrs::Button::LeftTrigger => Some(Button::LeftShoulder),
+        gilrs::Button::RightTrigger => Some(Button::RightShoulder),
+        gilrs::Button::Select => Some(Button::Select),
+        gilrs::Button::Start => Some(Button::Start),
+        _ => None,
+    }
+}
+
+fn axis_of(axis: gilrs::Axis) -> Option<Axis> {
+    match axis {
+        gilrs::Axis::LeftStickX => Some(Axis::LeftX),
+        gilrs::Axis::LeftStickY => Some(Axis::LeftY),
+        gilrs::Axis::RightStickX => Some(Axis::RightX),
+        gilrs::Axis::RightStickY => Some(Axis::RightY),
+        _ => None,
+    }
+}
@@ -0,0 +1,95 @@
+mod bsp;
+mod caves;
+mod grid;
+mod rooms;
+
+use rand::{rngs::StdRng, SeedableRng};
+
+pub use grid::{Grid, Terrain};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Algorithm {
+    Rooms,
+    Bsp,
+    Caves,
+}
+
+pub struct Generator {
+    pub algorithm: Algorithm,
+    pub width: usize,
+    pub height: usize,
+    pub spawns: usize,
+    rng: StdRng,
+}
+
+impl Generator {
+    pub fn new(algorithm: Algorithm, width: usize, height: usize, seed: u64) -> Self {
+        Generator {
+            algorithm,
+            width,
+            height,
+            spawns: 4,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    pub fn generate(&mut self) -> Grid {
+        let mut grid = Grid::new(self.width, self.height);
+
+        match self.algorithm {
+            Algorithm::Rooms => rooms::carve(&mut grid, &mut self.rng),
+            Algorithm::Bsp => bsp::carve(&mut grid, &mut self.rng),
+            Algorithm::Caves => caves::carve(&mut grid, &mut self.rng),
+        }
+
+        grid.place_features(self.spawns, &mut self.rng);
+
+        grid
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::TileMap;
+    use crate::validation;
+
+    const ALGORITHMS: [Algorithm; 3] = [Algorithm::Rooms, Algorithm::Bsp, Algorithm::Caves];
+
+    #[test]
+    fn same_seed_same_dungeon() {
+        for algorithm in ALGORITHMS {
+            let a = Generator::new(algorithm, 40, 30, 7).generate();
+            let b = Generator::new(algorithm, 40, 30, 7).generate();
+            assert_eq!(a.to_map(), b.to_map(), "{:?}", algorithm);
+        }
+    }
+
+    #[test]
+    fn stairs_and_spawns_reachable_from_start() {
+        for algorithm in ALGORITHMS {
+            for seed in 0..20 {
+                let grid = Generator::new(algorithm, 40, 30, seed).generate();
+                let distances = grid.distances(grid.start);
+                let reachable = |(x, y): (usize, usize)| distances[y * grid.width + x].is_some();
+
+                assert_ne!(grid.start, grid.stairs, "{:?} seed {}", algorithm, seed);
+                assert!(reachable(grid.stairs), "{:?} seed {}", algorithm, seed);
+                assert!(grid.spawns.iter().all(|&c| reachable(c)));
+            }
+        }
+    }
+
+    #[test]
+    fn generated_maps_are_valid() {
+        for algorithm in ALGORITHMS {
+            let grid = Generator::new(algorithm, 40, 30, 3).generate();
+
+            let mut map = TileMap::new();
+            map.load(&grid.to_map(), (), ()).unwrap();
+
+            let diagnostics = validation::validate(&map);
+            assert!(!validation::has_errors(&diagnostics), "{:?}", diagnostics);
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use rand::{rngs::StdRng, Rng};
+
+use super::Grid;
+
+const MIN_LEAF: usize = 6;
+
+/// Split the grid recursively and put a room in every leaf. Sibling
+/// partitions are connected through their centers.
+pub fn carve(grid: &mut Grid, rng: &mut StdRng) {
+    let (w, h) = (grid.width, grid.height);
+
+    if w < 3 || h < 3 {
+        return;
+    }
+
+    split(grid, rng, 1, 1, w - 2, h - 2);
+}
+
+fn split(
+    grid: &mut Grid,
+    rng: &mut StdRng,
+    x: usize,
+    y: usize,
+    w: usize,
+    h: usize,
+) -> (usize, usize) {
+    let can_split_x = w >= 2 * MIN_LEAF;
+    let can_split_y = h >= 2 * MIN_LEAF;
+
+    let vertical = match (can_split_x, can_split_y) {
+        (false, false) => return room(grid, rng, x, y, w, h),
+        (true, false) => true,
+        (false, true) => false,
+        (true, true) => w > h || (w == h && rng.gen_bool(0.5)),
+    };
+
+    let (a, b) = if vertical {
+        let cut = rng.gen_range(MIN_LEAF..=w - MIN_LEAF);
+        (
+            split(grid, rng, x, y, cut, h),
+            split(grid, rng, x + cut, y, w - cut, h),
+        )
+    } else {
+        let cut = rng.gen_range(MIN_LEAF..=h - MIN_LEAF);
+        (
+            split(grid, rng, x, y, w, cut),
+            split(grid, rng, x, y + cut, w, h - cut),
+        )
+    };
+
+    grid.carve_corridor(a, b, rng);
+
+    if rng.gen_bool(0.5) {
+        a
+    } else {
+        b
+    }
+}
+
+fn room(
+    grid: &mut Grid,
+    rng: &mut StdRng,
+    x: usize,
+    y: usize,
+    w: usize,
+    h: usize,
+) -> (usize, usize) {
+    // keep the last column and row of the partition as wall, so that the
+    // room never touches the room of the next partition
+    let rw = rng.gen_range((w / 2).max(1)..=(w - 1).max(1));
+    let rh = rng.gen_range((h / 2).max(1)..=(h - 1).max(1));
+    let rx = x + rng.gen_range(0..=(w - rw).saturating_sub(1));
+    let ry = y + rng.gen_range(0..=(h - rh).saturating_sub(1));
+
+    grid.carve_room(rx, ry, rw, rh);
+
+    (rx + rw / 2, ry + rh / 2)
+}
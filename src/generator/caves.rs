@@ -0,0 +1,55 @@
+use rand::{rngs::StdRng, Rng};
+
+use super::{Grid, Terrain};
+
+const FILL: f64 = 0.45;
+const STEPS: usize = 5;
+
+/// Cellular automaton: start from random noise and smooth it, a cell
+/// becoming a wall when most of its neighbours are walls.
+pub fn carve(grid: &mut Grid, rng: &mut StdRng) {
+    for y in 0..grid.height {
+        for x in 0..grid.width {
+            if !rng.gen_bool(FILL) {
+                grid.carve(x, y);
+            }
+        }
+    }
+
+    for _ in 0..STEPS {
+        let mut next = Grid::new(grid.width, grid.height);
+
+        for y in 0..grid.height {
+            for x in 0..grid.width {
+                let walls = walls_around(grid, x, y);
+                let wall = match grid.get(x, y) {
+                    Terrain::Wall => walls >= 4,
+                    Terrain::Floor => walls >= 5,
+                };
+                if !wall {
+                    next.carve(x, y);
+                }
+            }
+        }
+
+        *grid = next;
+    }
+}
+
+fn walls_around(grid: &Grid, x: usize, y: usize) -> usize {
+    let mut walls = 0;
+
+    for j in y as isize - 1..=y as isize + 1 {
+        for i in x as isize - 1..=x as isize + 1 {
+            if (i, j) == (x as isize, y as isize) {
+                continue;
+            }
+            let outside = i < 0 || j < 0 || i as usize >= grid.width || j as usize >= grid.height;
+            if outside || grid.get(i as usize, j as usize) == Terrain::Wall {
+                walls += 1;
+            }
+        }
+    }
+
+    walls
+}
@@ -0,0 +1,218 @@
+use std::collections::VecDeque;
+use std::fs;
+use std::path::Path;
+
+use anyhow::Result;
+use rand::{rngs::StdRng, seq::SliceRandom};
+
+use crate::map::TileMap;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Terrain {
+    Wall,
+    Floor,
+}
+
+pub struct Grid {
+    pub width: usize,
+    pub height: usize,
+    pub start: (usize, usize),
+    pub stairs: (usize, usize),
+    pub spawns: Vec<(usize, usize)>,
+    cells: Vec<Terrain>,
+}
+
+impl Grid {
+    pub fn new(width: usize, height: usize) -> Self {
+        Grid {
+            width,
+            height,
+            start: (0, 0),
+            stairs: (0, 0),
+            spawns: Vec::new(),
+            cells: vec![Terrain::Wall; width * height],
+        }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Terrain {
+        self.cells[y * self.width + x]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, terrain: Terrain) {
+        self.cells[y * self.width + x] = terrain;
+    }
+
+    /// Carve a floor cell, leaving the outer ring of the grid untouched.
+    pub fn carve(&mut self, x: usize, y: usize) {
+        if x > 0 && y > 0 && x < self.width - 1 && y < self.height - 1 {
+            self.set(x, y, Terrain::Floor);
+        }
+    }
+
+    pub fn carve_room(&mut self, x: usize, y: usize, w: usize, h: usize) {
+        for j in y..y + h {
+            for i in x..x + w {
+                self.carve(i, j);
+            }
+        }
+    }
+
+    /// Carve an L-shaped corridor between two cells.
+    pub fn carve_corridor(&mut self, from: (usize, usize), to: (usize, usize), rng: &mut StdRng) {
+        let horizontal_first = rand::Rng::gen_bool(rng, 0.5);
+
+        let corner = if horizontal_first {
+            (to.0, from.1)
+        } else {
+            (from.0, to.1)
+        };
+
+        for (a, b) in [(from, corner), (corner, to)] {
+            for j in a.1.min(b.1)..=a.1.max(b.1) {
+                for i in a.0.min(b.0)..=a.0.max(b.0) {
+                    self.carve(i, j);
+                }
+            }
+        }
+    }
+
+    pub fn floors(&self) -> Vec<(usize, usize)> {
+        (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+            .filter(|&(x, y)| self.get(x, y) == Terrain::Floor)
+            .collect()
+    }
+
+    /// Breadth first walk over floor cells, returning the distance to every
+    /// reachable cell (`None` for walls and unreachable floors).
+    pub fn distances(&self, from: (usize, usize)) -> Vec<Option<usize>> {
+        let mut distances = vec![None; self.width * self.height];
+        let mut queue = VecDeque::new();
+
+        distances[from.1 * self.width + from.0] = Some(0);
+        queue.push_back(from);
+
+        while let Some((x, y)) = queue.pop_front() {
+            let d = distances[y * self.width + x].unwrap();
+
+            for (nx, ny) in [
+                (x + 1, y),
+                (x.wrapping_sub(1), y),
+                (x, y + 1),
+                (x, y.wrapping_sub(1)),
+            ] {
+                if nx >= self.width || ny >= self.height {
+                    continue;
+                }
+                let idx = ny * self.width + nx;
+                if self.get(nx, ny) == Terrain::Floor && distances[idx].is_none() {
+                    distances[idx] = Some(d + 1);
+                    queue.push_back((nx, ny));
+                }
+            }
+        }
+
+        distances
+    }
+
+    /// Fill every floor region except the largest one, so that the whole
+    /// level is reachable from any floor cell.
+    pub fn keep_largest_region(&mut self) {
+        let mut region = vec![None; self.width * self.height];
+        let mut sizes = Vec::new();
+
+        for (x, y) in self.floors() {
+            if region[y * self.width + x].is_some() {
+                continue;
+            }
+            let id = sizes.len();
+            let mut size = 0;
+            for (idx, d) in self.distances((x, y)).iter().enumerate() {
+                if d.is_some() {
+                    region[idx] = Some(id);
+                    size += 1;
+                }
+            }
+            sizes.push(size);
+        }
+
+        let largest = (0..sizes.len()).max_by_key(|&id| sizes[id]);
+
+        for (idx, r) in region.iter().enumerate() {
+            if r.is_some() && *r != largest {
+                self.cells[idx] = Terrain::Wall;
+            }
+        }
+    }
+
+    /// Pick the start, stairs and spawn points among the cells reachable
+    /// from the start. Stairs are put on the farthest cell, never on the
+    /// start unless it is the only floor cell.
+    pub fn place_features(&mut self, spawns: usize, rng: &mut StdRng) {
+        self.keep_largest_region();
+
+        let floors = self.floors();
+        let Some(&start) = floors.choose(rng) else {
+            return;
+        };
+        self.start = start;
+
+        let distances = self.distances(start);
+
+        let reachable = |&(x, y): &(usize, usize)| distances[y * self.width + x];
+
+        self.stairs = floors
+            .iter()
+            .copied()
+            .filter(|&c| c != start && reachable(&c).is_some())
+            .max_by_key(|c| reachable(c))
+            .unwrap_or(start);
+
+        let candidates: Vec<(usize, usize)> = floors
+            .iter()
+            .copied()
+            .filter(|&c| c != self.start && c != self.stairs && reachable(&c).is_some())
+            .collect();
+
+        self.spawns = candidates.choose_multiple(rng, spawns).copied().collect();
+    }
+
+    pub fn to_map(&self) -> String {
+        let mut data = String::with_capacity((self.width + 1) * self.height);
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let c = if (x, y) == self.start {
+                    '@'
+                } else if (x, y) == self.stairs {
+                    '>'
+                } else if self.spawns.contains(&(x, y)) {
+                    'm'
+                } else {
+                    match self.get(x, y) {
+                        Terrain::Wall => 'w',
+                        Terrain::Floor => '.',
+                    }
+                };
+                data.push(c);
+            }
+            data.push('\n');
+        }
+
+        data
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        fs::write(path, self.to_map())?;
+
+        Ok(())
+    }
+
+    pub fn build<M: Clone>(&self, wall: M, floor: M) -> Result<TileMap<M>> {
+        let mut map = TileMap::new();
+
+        map.load(&self.to_map(), wall, floor)?;
+
+        Ok(map)
+    }
+}
@@ -0,0 +1,58 @@
+use rand::{rngs::StdRng, Rng};
+
+use super::Grid;
+
+const ATTEMPTS: usize = 50;
+const MIN_SIZE: usize = 3;
+const MAX_SIZE: usize = 8;
+
+struct Room {
+    x: usize,
+    y: usize,
+    w: usize,
+    h: usize,
+}
+
+impl Room {
+    fn center(&self) -> (usize, usize) {
+        (self.x + self.w / 2, self.y + self.h / 2)
+    }
+
+    fn overlaps(&self, other: &Room) -> bool {
+        self.x <= other.x + other.w
+            && other.x <= self.x + self.w
+            && self.y <= other.y + other.h
+            && other.y <= self.y + self.h
+    }
+}
+
+/// Scatter non overlapping rooms and join each one to the previous with a
+/// corridor.
+pub fn carve(grid: &mut Grid, rng: &mut StdRng) {
+    let mut rooms: Vec<Room> = Vec::new();
+
+    if grid.width < MIN_SIZE + 2 || grid.height < MIN_SIZE + 2 {
+        return;
+    }
+
+    for _ in 0..ATTEMPTS {
+        let w = rng.gen_range(MIN_SIZE..=MAX_SIZE.min(grid.width - 2));
+        let h = rng.gen_range(MIN_SIZE..=MAX_SIZE.min(grid.height - 2));
+        let x = rng.gen_range(1..grid.width - w);
+        let y = rng.gen_range(1..grid.height - h);
+
+        let room = Room { x, y, w, h };
+
+        if rooms.iter().any(|r| r.overlaps(&room)) {
+            continue;
+        }
+
+        grid.carve_room(room.x, room.y, room.w, room.h);
+
+        if let Some(previous) = rooms.last() {
+            grid.carve_corridor(previous.center(), room.center(), rng);
+        }
+
+        rooms.push(room);
+    }
+}
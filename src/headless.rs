@@ -0,0 +1,134 @@
+use anyhow::{anyhow, Result};
+use hecs::World;
+use rand::{rngs::StdRng, SeedableRng};
+
+use crate::components::{Door, Orientation, Player, Position, Secret};
+use crate::config::Settings;
+use crate::lighting::Lighting;
+use crate::map::{Cell, TileMap};
+use crate::movement::Facing;
+use crate::options::Options;
+use crate::save::SaveGame;
+use crate::scripting::Scripts;
+use crate::{spawner, systems};
+
+const TICK: f32 = 1. / 60.;
+
+/// Run the simulation for a number of ticks without creating a window, then
+/// print the level the player is on, after writing the save file if one is
+/// given.
+pub fn run(options: &Options, ticks: u32) -> Result<()> {
+    let settings = Settings::load(options)?;
+
+    let mut map = TileMap::new();
+    map.load(&options.map_data()?, (), ())?;
+
+    let start = map
+        .start_on(options.level as i32)
+        .ok_or_else(|| anyhow!("No start position on level {}", options.level))?;
+
+    let mut world = World::new();
+    spawner::spawn_player(&mut world, start, &settings);
+    spawner::spawn_map_entities(&mut world, &map);
+
+    if let Some(path) = &options.load {
+        SaveGame::read(path)?.restore(&mut world);
+    }
+
+    let mut rng = StdRng::seed_from_u64(options.seed);
+    let mut scripts = Scripts::load(&map, &options.script_dir())?;
+    let mut lighting = Lighting::new(&map);
+
+    for _ in 0..ticks {
+        let mut events = Vec::new();
+        systems::simulate(
+            TICK,
+            &mut world,
+            &mut events,
+            &map,
+            &settings,
+            &mut rng,
+            &mut scripts,
+            &mut lighting,
+        );
+    }
+
+    if let Some(path) = &options.save {
+        if let Some(game) = SaveGame::capture(&world) {
+            game.write(path)?;
+        }
+    }
+
+    print!("{}", render(&map, &world));
+
+    Ok(())
+}
+
+pub fn render<M: Clone>(map: &TileMap<M>, world: &World) -> String {
+    let mut query = world.query::<(&Player, &Position, &Orientation)>();
+    let Some((_, (_, position, orientation))) = query.iter().next() else {
+        return String::new();
+    };
+
+    let player = map.cell((*position).into());
+
+    let Some((min, max)) = map.bounds(player.level) else {
+        return String::new();
+    };
+
+    let doors: Vec<(Cell, char)> = world
+        .query::<(&Door, &Position, Option<&Secret>)>()
+        .iter()
+        .map(|(_, (door, position, secret))| {
+            let c = match (door.open, secret) {
+                (true, _) => '/',
+                (false, Some(_)) => '#',
+                (false, None) => '+',
+            };
+            (map.cell((*position).into()), c)
+        })
+        .collect();
+
+    let mut output = String::new();
+
+    for y in min.y..=max.y {
+        for x in min.x..=max.x {
+            let cell = Cell::new(x, y, player.level);
+
+            let c = if cell == player {
+                match orientation.facing {
+                    Facing::North => '^',
+                    Facing::South => 'v',
+                    Facing::East => '>',
+                    Facing::West => '<',
+                    Facing::NorthEast => '9',
+                    Facing::SouthEast => '3',
+                    Facing::SouthWest => '1',
+                    Facing::NorthWest => '7',
+                }
+            } else if let Some((_, c)) = doors.iter().find(|(c, _)| *c == cell) {
+                *c
+            } else if map.is_wall(cell) {
+                '#'
+            } else if map.is_climbable(cell) {
+                'H'
+            } else if map.is_open(cell) {
+                'O'
+            } else if map.is_floor(cell) {
+                '.'
+            } else {
+                ' '
+            };
+
+            output.push(c);
+        }
+        output.push('\n');
+    }
+
+    output.push_str(&format!(
+        "position: {} facing: {:?}\n",
+        player, orientation.facing
+    ));
+
+    output
+}
@@ -0,0 +1,624 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use glam::Vec3;
+use gobs::game::input::Input;
+use hecs::World;
+use serde::{Deserialize, Serialize};
+
+mod pointer;
+
+pub use pointer::{Pointer, View};
+
+use crate::components::{Action, Door, Health, Item, Orientation, Player, Position, Secret};
+use crate::config::InputSettings;
+use crate::events::Event;
+use crate::map::{Cell, FeatureKind, TileMap, TileSet};
+use crate::movement::{self, Direction, Facing};
+
+/// How far clicks in the view reach, in tiles.
+const PICK_DISTANCE: f32 = 2.;
+
+/// Area of the screen, in fractions of the screen size for the layout and
+/// in pixels for the computed elements.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Rect {
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    fn scale(&self, width: f32, height: f32) -> Rect {
+        Rect::new(
+            self.x * width,
+            self.y * height,
+            self.width * width,
+            self.height * height,
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct PartyLayout {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    /// Distance between the bars of two party members
+    pub row_height: f32,
+    pub bar_height: f32,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct MinimapLayout {
+    #[serde(flatten)]
+    pub rect: Rect,
+    /// Number of cells shown across, for every zoom level
+    pub zoom: Vec<u32>,
+}
+
+/// Placement of the HUD elements, read from a data file.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Layout {
+    pub compass: Rect,
+    pub party: PartyLayout,
+    pub minimap: MinimapLayout,
+    pub full_map: Rect,
+    /// Movement buttons, in two rows of three
+    pub controls: Rect,
+}
+
+impl Layout {
+    pub fn parse(data: &str) -> Result<Self> {
+        Ok(toml::from_str(data)?)
+    }
+
+    /// Layout shipped with the game.
+    pub fn builtin() -> Self {
+        Self::parse(crate::HUD).expect("Invalid built-in HUD layout")
+    }
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Widget {
+    /// Gauge filled from the left, from 0 to 1
+    Bar(f32),
+    /// Needle pointing to the facing
+    Compass(Facing),
+    /// Map cell
+    Tile(MapTile),
+    /// Party on the map, turned by a yaw
+    Arrow(f32),
+    /// Clickable button doing an action
+    Button(Action),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MapTile {
+    Wall,
+    Floor,
+    Door,
+    /// Hole, ladder or rope
+    Opening,
+}
+
+/// Map tile of every cell, worked out once per map load rather than
+/// searching the map for each cell drawn.
+#[derive(Clone, Debug, Default)]
+pub struct Plan {
+    tiles: HashMap<Cell, MapTile>,
+    /// Lowest and highest cell of each level
+    bounds: HashMap<i32, (Cell, Cell)>,
+}
+
+impl Plan {
+    pub fn new<M: Clone>(map: &TileMap<M>) -> Self {
+        let mut plan = Plan::default();
+
+        // Walls hide the openings, which replace the floor
+        for tile in &map.tiles {
+            if let TileSet::FLOOR(_) = tile.tile {
+                plan.set(map.cell(tile.position), MapTile::Floor);
+            }
+        }
+        for feature in &map.features {
+            if let FeatureKind::Hole | FeatureKind::Ladder | FeatureKind::Rope = feature.kind {
+                plan.set(map.cell(feature.position), MapTile::Opening);
+            }
+        }
+        for tile in &map.tiles {
+            if let TileSet::WALL(_) = tile.tile {
+                plan.set(map.cell(tile.position), MapTile::Wall);
+            }
+        }
+
+        plan
+    }
+
+    fn set(&mut self, cell: Cell, tile: MapTile) {
+        self.tiles.insert(cell, tile);
+
+        let bounds = self.bounds.entry(cell.level).or_insert((cell, cell));
+        bounds.0 = Cell::new(bounds.0.x.min(cell.x), bounds.0.y.min(cell.y), cell.level);
+        bounds.1 = Cell::new(bounds.1.x.max(cell.x), bounds.1.y.max(cell.y), cell.level);
+    }
+
+    pub fn tile(&self, cell: Cell) -> Option<MapTile> {
+        self.tiles.get(&cell).copied()
+    }
+
+    pub fn bounds(&self, level: i32) -> Option<(Cell, Cell)> {
+        self.bounds.get(&level).copied()
+    }
+}
+
+/// HUD element placed on the screen, in pixels.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Element {
+    pub rect: Rect,
+    pub widget: Widget,
+}
+
+impl Element {
+    fn new(rect: Rect, widget: Widget) -> Self {
+        Element { rect, widget }
+    }
+}
+
+/// State of the HUD, turned into elements to draw each frame. Only shapes
+/// are drawn, as gobs cannot render text: there is no message log and no
+/// party names, the messages go to the log output.
+#[derive(Clone, Debug, Default)]
+pub struct Hud {
+    pub layout: Layout,
+    /// Index of the minimap zoom level
+    pub zoom: usize,
+    /// Show the whole level instead of the minimap
+    pub full_map: bool,
+    /// Tiles of the loaded map
+    pub plan: Plan,
+}
+
+impl Hud {
+    pub fn new(layout: Layout) -> Self {
+        Hud {
+            layout,
+            zoom: 0,
+            full_map: false,
+            plan: Plan::default(),
+        }
+    }
+
+    /// Work out the map tiles, on every map load.
+    pub fn load<M: Clone>(&mut self, map: &TileMap<M>) {
+        self.plan = Plan::new(map);
+    }
+
+    /// Handle the map keys of the settings.
+    pub fn update(&mut self, events: &[Event], settings: &InputSettings) {
+        for event in events {
+            match event {
+                Event::Input(Input::KeyPressed(key)) if settings.is_key(key, &settings.map_key) => {
+                    self.full_map = !self.full_map
+                }
+                Event::Input(Input::KeyPressed(key))
+                    if settings.is_key(key, &settings.zoom_in_key) =>
+                {
+                    self.zoom = self.zoom.saturating_sub(1)
+                }
+                Event::Input(Input::KeyPressed(key))
+                    if settings.is_key(key, &settings.zoom_out_key) =>
+                {
+                    let levels = self.layout.minimap.zoom.len();
+                    self.zoom = (self.zoom + 1).min(levels.saturating_sub(1));
+                }
+                _ => (),
+            }
+        }
+    }
+
+    /// Elements of the HUD for a screen size, in pixels.
+    pub fn elements<M: Clone>(
+        &self,
+        world: &World,
+        map: &TileMap<M>,
+        width: f32,
+        height: f32,
+    ) -> Vec<Element> {
+        let mut elements = Vec::new();
+
+        self.compass(world, width, height, &mut elements);
+        self.party(world, width, height, &mut elements);
+        self.map(world, map, width, height, &mut elements);
+        self.controls(width, height, &mut elements);
+
+        elements
+    }
+
+    /// Action for a click on the screen: a button, the items on the floor
+    /// ahead of the party or the cell ahead in the view.
+    #[allow(clippy::too_many_arguments)]
+    pub fn click<M: Clone>(
+        &self,
+        world: &World,
+        map: &TileMap<M>,
+        view: &View,
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+    ) -> Option<Action> {
+        let inside = |rect: &Rect| {
+            x >= rect.x && x < rect.x + rect.width && y >= rect.y && y < rect.y + rect.height
+        };
+
+        let button = self
+            .elements(world, map, width, height)
+            .into_iter()
+            .find_map(|element| match element.widget {
+                Widget::Button(action) if inside(&element.rect) => Some(action),
+                _ => None,
+            });
+        if button.is_some() {
+            return button;
+        }
+
+        let (position, orientation) = world
+            .query::<(&Position, &Orientation)>()
+            .with::<&Player>()
+            .iter()
+            .next()
+            .map(|(_, (p, o))| (*p, *o))?;
+
+        let here = map.cell(position.into());
+        let front = map.cell(
+            Into::<Vec3>::into(position)
+                + movement::get_translation(orientation.facing, Direction::Forward, 1.),
+        );
+
+        let items = world
+            .query::<(&Item, &Position)>()
+            .iter()
+            .any(|(_, (_, p))| map.cell((*p).into()) == front);
+        let blocked = map.is_wall(front)
+            || world
+                .query::<(&Door, &Position)>()
+                .iter()
+                .any(|(_, (door, p))| !door.open && map.cell((*p).into()) == front);
+
+        // Follow the ray out of the party cell into the cell ahead, until it
+        // meets a wall, a closed door, the floor or the ceiling
+        let floor = map.floor_position(here).y + 0.5;
+        let mut ahead = false;
+
+        for point in view.ray(x, y, width, height, PICK_DISTANCE) {
+            let cell = map.cell(point);
+
+            if point.y < floor {
+                if cell == front && items {
+                    return Some(Action::PickUp);
+                }
+                break;
+            }
+            if point.y > floor + 1. || (cell != here && cell != front) {
+                break;
+            }
+            if cell == front {
+                ahead = true;
+                if blocked {
+                    break;
+                }
+            }
+        }
+
+        ahead.then_some(Action::Interact)
+    }
+
+    fn controls(&self, width: f32, height: f32, elements: &mut Vec<Element>) {
+        let rect = self.layout.controls.scale(width, height);
+        let (column, row) = (rect.width / 3., rect.height / 2.);
+
+        let buttons = [
+            Action::Turn(Direction::Left),
+            Action::Move(Direction::Forward),
+            Action::Turn(Direction::Right),
+            Action::Move(Direction::Left),
+            Action::Move(Direction::Backward),
+            Action::Move(Direction::Right),
+        ];
+
+        for (i, action) in buttons.into_iter().enumerate() {
+            let button = Rect::new(
+                rect.x + (i % 3) as f32 * column,
+                rect.y + (i / 3) as f32 * row,
+                column,
+                row,
+            );
+            elements.push(Element::new(button, Widget::Button(action)));
+        }
+    }
+
+    /// Cells around the party, or the whole level when the full map is
+    /// shown.
+    fn map<M: Clone>(
+        &self,
+        world: &World,
+        map: &TileMap<M>,
+        width: f32,
+        height: f32,
+        elements: &mut Vec<Element>,
+    ) {
+        let Some((position, orientation)) = world
+            .query::<(&Position, &Orientation)>()
+            .with::<&Player>()
+            .iter()
+            .next()
+            .map(|(_, (p, o))| (*p, *o))
+        else {
+            return;
+        };
+
+        let player = map.cell(position.into());
+
+        let (rect, min, max) = if self.full_map {
+            let Some((min, max)) = self.plan.bounds(player.level) else {
+                return;
+            };
+            (self.layout.full_map.scale(width, height), min, max)
+        } else {
+            let zoom = &self.layout.minimap.zoom;
+            let cells = zoom.get(self.zoom).or(zoom.last()).copied().unwrap_or(1) as i32;
+            let min = Cell::new(player.x - cells / 2, player.y - cells / 2, player.level);
+            let max = Cell::new(min.x + cells - 1, min.y + cells - 1, player.level);
+            (self.layout.minimap.rect.scale(width, height), min, max)
+        };
+
+        // Square cells, the map centered in its area
+        let columns = (max.x - min.x + 1) as f32;
+        let rows = (max.y - min.y + 1) as f32;
+        let size = (rect.width / columns).min(rect.height / rows);
+        let left = rect.x + (rect.width - size * columns) / 2.;
+        let top = rect.y + (rect.height - size * rows) / 2.;
+        let at = |cell: Cell| {
+            Rect::new(
+                left + (cell.x - min.x) as f32 * size,
+                top + (cell.y - min.y) as f32 * size,
+                size,
+                size,
+            )
+        };
+
+        // Secret doors look like walls
+        let doors: Vec<(Cell, bool)> = world
+            .query::<(&Door, &Position)>()
+            .without::<&Secret>()
+            .iter()
+            .map(|(_, (door, p))| (map.cell((*p).into()), door.open))
+            .collect();
+
+        for y in min.y..=max.y {
+            for x in min.x..=max.x {
+                let cell = Cell::new(x, y, player.level);
+
+                let tile = if doors.iter().any(|(c, open)| *c == cell && !open) {
+                    MapTile::Door
+                } else if let Some(tile) = self.plan.tile(cell) {
+                    tile
+                } else {
+                    continue;
+                };
+
+                elements.push(Element::new(at(cell), Widget::Tile(tile)));
+            }
+        }
+
+        elements.push(Element::new(at(player), Widget::Arrow(orientation.yaw)));
+    }
+
+    fn compass(&self, world: &World, width: f32, height: f32, elements: &mut Vec<Element>) {
+        let Some((_, orientation)) = world
+            .query::<&Orientation>()
+            .with::<&Player>()
+            .iter()
+            .next()
+            .map(|(e, o)| (e, *o))
+        else {
+            return;
+        };
+
+        let rect = self.layout.compass.scale(width, height);
+
+        elements.push(Element::new(rect, Widget::Compass(orientation.facing)));
+    }
+
+    fn party(&self, world: &World, width: f32, height: f32, elements: &mut Vec<Element>) {
+        let layout = &self.layout.party;
+
+        for (row, (_, health)) in world
+            .query::<&Health>()
+            .with::<&Player>()
+            .iter()
+            .enumerate()
+        {
+            let bar = Rect::new(
+                layout.x,
+                layout.y + row as f32 * layout.row_height,
+                layout.width,
+                layout.bar_height,
+            );
+
+            let fill = if health.max > 0 {
+                health.current as f32 / health.max as f32
+            } else {
+                0.
+            };
+
+            elements.push(Element::new(bar.scale(width, height), Widget::Bar(fill)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gobs::game::input::Key;
+
+    use super::*;
+
+    const LAYOUT: &str = r#"
+        compass = { x = 0.5, y = 0, width = 0.25, height = 0.125 }
+        party = { x = 0, y = 0, width = 0.25, row_height = 0.125, bar_height = 0.0625 }
+        minimap = { x = 0.75, y = 0, width = 0.25, height = 0.25, zoom = [3] }
+        full_map = { x = 0, y = 0, width = 1, height = 1 }
+        controls = { x = 0.75, y = 0.75, width = 0.25, height = 0.25 }
+    "#;
+
+    fn world() -> (World, TileMap<()>) {
+        let mut map = TileMap::new();
+        map.load("wwww\nw@.w\nwwww\n", (), ()).unwrap();
+
+        let mut world = World::new();
+        let mut health = Health::new(20);
+        health.current = 10;
+        world.spawn((
+            Player,
+            health,
+            Position::from(map.start),
+            Orientation::new(Facing::East),
+        ));
+
+        (world, map)
+    }
+
+    #[test]
+    fn compass_shows_the_facing() {
+        let (world, map) = world();
+        let hud = Hud::new(Layout::parse(LAYOUT).unwrap());
+        let elements = hud.elements(&world, &map, 800., 400.);
+
+        assert!(elements.contains(&Element::new(
+            Rect::new(400., 0., 200., 50.),
+            Widget::Compass(Facing::East)
+        )));
+    }
+
+    #[test]
+    fn party_members_have_health_bars_in_rows() {
+        let (mut world, map) = world();
+        world.spawn((Player, Health::new(10)));
+        let hud = Hud::new(Layout::parse(LAYOUT).unwrap());
+        let elements = hud.elements(&world, &map, 800., 400.);
+
+        let mut bars: Vec<Element> = elements
+            .into_iter()
+            .filter(|e| matches!(e.widget, Widget::Bar(_)))
+            .collect();
+        bars.sort_by(|a, b| a.rect.y.total_cmp(&b.rect.y));
+
+        assert_eq!(
+            bars,
+            [
+                Element::new(Rect::new(0., 0., 200., 25.), Widget::Bar(0.5)),
+                Element::new(Rect::new(0., 50., 200., 25.), Widget::Bar(1.)),
+            ]
+        );
+    }
+
+    #[test]
+    fn minimap_shows_walls_and_floors() {
+        let (world, map) = world();
+        let mut hud = Hud::new(Layout::parse(LAYOUT).unwrap());
+        hud.load(&map);
+        let elements = hud.elements(&world, &map, 800., 400.);
+
+        let tiles = |tile| {
+            elements
+                .iter()
+                .filter(|e| e.widget == Widget::Tile(tile))
+                .count()
+        };
+        assert_eq!(tiles(MapTile::Wall), 7);
+        assert_eq!(tiles(MapTile::Floor), 2);
+    }
+
+    #[test]
+    fn map_keys_come_from_the_settings() {
+        let (world, _) = world();
+        let mut hud = Hud::new(Layout::parse(LAYOUT).unwrap());
+        let mut settings = InputSettings {
+            map_key: "M".into(),
+            ..Default::default()
+        };
+
+        hud.update(&[Event::Input(Input::KeyPressed(Key::M))], &settings);
+        assert!(hud.full_map);
+
+        settings.map_key = "Tab".into();
+        hud.update(&[Event::Input(Input::KeyPressed(Key::M))], &settings);
+        assert!(hud.full_map);
+    }
+
+    /// Click the middle of the screen, the party looking up or down by a
+    /// pitch.
+    fn click(world: &World, map: &TileMap<()>, facing: Facing, pitch: f32) -> Option<Action> {
+        for (_, orientation) in world.query::<&mut Orientation>().iter() {
+            *orientation = Orientation::new(facing);
+        }
+
+        let hud = Hud::new(Layout::parse(LAYOUT).unwrap());
+        let view = View {
+            position: map.start,
+            yaw: facing.yaw(),
+            pitch,
+            fov: 1.,
+        };
+        hud.click(world, map, &view, 400., 200., 800., 400.)
+    }
+
+    #[test]
+    fn click_picks_up_items_on_the_floor_ahead() {
+        let (mut world, map) = world();
+        assert_eq!(
+            click(&world, &map, Facing::East, -0.4),
+            Some(Action::Interact)
+        );
+
+        let front = map.position(Cell::new(2, 1, 0));
+        world.spawn((
+            Item {
+                name: "dagger".into(),
+            },
+            Position::from(front),
+        ));
+        assert_eq!(
+            click(&world, &map, Facing::East, -0.4),
+            Some(Action::PickUp)
+        );
+        assert_eq!(click(&world, &map, Facing::East, -1.2), None);
+    }
+
+    #[test]
+    fn click_interacts_with_the_wall_ahead() {
+        let (world, map) = world();
+        assert_eq!(
+            click(&world, &map, Facing::West, 0.),
+            Some(Action::Interact)
+        );
+        assert_eq!(click(&world, &map, Facing::West, 1.2), None);
+    }
+}
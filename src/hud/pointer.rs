@@ -0,0 +1,100 @@
+use glam::Vec3;
+use gobs::game::input::Input;
+
+/// Distance the mouse can move while pressed for a click, in pixels.
+const CLICK_DRAG: f32 = 4.;
+/// Steps per tile when following a ray through the cells.
+const RAY_STEPS: f32 = 20.;
+
+/// Mouse cursor on the screen. gobs only reports mouse motion, so the
+/// cursor starts at the center of the screen and follows the motion, which
+/// can drift from the system cursor.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Pointer {
+    pub x: f32,
+    pub y: f32,
+    pressed: bool,
+    drag: f32,
+}
+
+impl Pointer {
+    pub fn new(width: f32, height: f32) -> Self {
+        Pointer {
+            x: width / 2.,
+            y: height / 2.,
+            pressed: false,
+            drag: 0.,
+        }
+    }
+
+    /// Follow the mouse. Returns the cursor position when the button is
+    /// released without dragging the view.
+    pub fn input(&mut self, input: &Input, width: f32, height: f32) -> Option<(f32, f32)> {
+        match input {
+            Input::MouseMotion(dx, dy) => {
+                let (dx, dy) = (*dx as f32, *dy as f32);
+                self.x = (self.x + dx).clamp(0., width);
+                self.y = (self.y + dy).clamp(0., height);
+                if self.pressed {
+                    self.drag += dx.abs() + dy.abs();
+                }
+                None
+            }
+            Input::MousePressed => {
+                self.pressed = true;
+                self.drag = 0.;
+                None
+            }
+            Input::MouseReleased => {
+                let click = self.pressed && self.drag < CLICK_DRAG;
+                self.pressed = false;
+                click.then_some((self.x, self.y))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// View through which the screen is picked.
+#[derive(Clone, Copy, Debug)]
+pub struct View {
+    pub position: Vec3,
+    pub yaw: f32,
+    pub pitch: f32,
+    /// Vertical field of view, in radians
+    pub fov: f32,
+}
+
+impl View {
+    /// Points along the ray under a screen position, from the eye up to a
+    /// distance.
+    pub fn ray(
+        &self,
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+        distance: f32,
+    ) -> impl Iterator<Item = Vec3> {
+        let (yaw, pitch) = (self.yaw, self.pitch);
+        let forward = Vec3::new(
+            yaw.cos() * pitch.cos(),
+            pitch.sin(),
+            yaw.sin() * pitch.cos(),
+        );
+        let right = Vec3::new(-yaw.sin(), 0., yaw.cos());
+        let up = right.cross(forward);
+
+        let half_height = (self.fov / 2.).tan();
+        let half_width = half_height * width / height;
+        let direction = (forward
+            + right * (x / width * 2. - 1.) * half_width
+            + up * (1. - y / height * 2.) * half_height)
+            .normalize();
+
+        let origin = self.position;
+        let steps = (distance * RAY_STEPS) as u32;
+
+        (1..=steps).map(move |step| origin + direction * step as f32 / RAY_STEPS)
+    }
+}
@@ -1,23 +1,50 @@
 pub mod app;
 pub mod components;
+pub mod config;
 pub mod events;
+pub mod gamepad;
+pub mod generator;
+pub mod headless;
+pub mod hud;
+pub mod lighting;
 pub mod map;
 pub mod movement;
+pub mod options;
+pub mod save;
+pub mod scripting;
+pub mod spawner;
+pub mod spells;
 pub mod systems;
+pub mod validation;
+pub mod watcher;
+
+use std::env;
+use std::path::PathBuf;
 
 use simplelog::{
     ColorChoice, CombinedLogger, ConfigBuilder, LevelFilter, TermLogger, TerminalMode,
 };
 
+pub const ASSETS_DIR: &str = "assets";
+pub const MAP_FILE: &str = "dungeon.map";
 pub const MAP: &str = include_str!("../assets/dungeon.map");
+pub const SPELLS: &str = include_str!("../assets/spells.toml");
+pub const HUD: &str = include_str!("../assets/hud.toml");
 pub const CUBE: &str = "cube.obj";
 pub const LIGHT: &str = "sphere.obj";
-pub const TILE_SIZE: f32 = 1.;
 pub const WALL_TEXTURE: &str = "tileset.png";
 pub const WALL_TEXTURE_N: &str = "normal.png";
 pub const WIRE_PASS: &str = "Wire";
 
-pub fn init_logger() {
+/// Assets copied next to the executable by the build script.
+pub fn assets_dir() -> PathBuf {
+    env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(|dir| dir.join(ASSETS_DIR)))
+        .unwrap_or_else(|| PathBuf::from(ASSETS_DIR))
+}
+
+pub fn init_logger(level: LevelFilter) {
     let config_other = ConfigBuilder::new()
         .add_filter_ignore_str("blobber")
         .build();
@@ -25,16 +52,11 @@ pub fn init_logger() {
 
     let _ = CombinedLogger::init(vec![
         TermLogger::new(
-            LevelFilter::Warn,
+            level.min(LevelFilter::Warn),
             config_other,
             TerminalMode::Mixed,
             ColorChoice::Auto,
         ),
-        TermLogger::new(
-            LevelFilter::Info,
-            config_self,
-            TerminalMode::Mixed,
-            ColorChoice::Auto,
-        ),
+        TermLogger::new(level, config_self, TerminalMode::Mixed, ColorChoice::Auto),
     ]);
 }
@@ -0,0 +1,336 @@
+use std::collections::{HashMap, HashSet};
+
+use glam::Vec3;
+use log::*;
+
+use crate::components::LightSource;
+use crate::map::{Cell, TileMap};
+
+/// Light level below which an entity is hidden in the dark.
+pub const HIDE_THRESHOLD: f32 = 0.2;
+
+/// Light level of every lit cell.
+#[derive(Debug, Default)]
+pub struct LightMap {
+    levels: HashMap<Cell, f32>,
+}
+
+impl LightMap {
+    /// Light level of a cell between 0 (dark) and 1 (fully lit).
+    pub fn level(&self, cell: Cell) -> f32 {
+        self.levels.get(&cell).copied().unwrap_or(0.)
+    }
+}
+
+/// Light of the current map: what blocks it, built once per map and kept
+/// up to date with the doors, and the light levels, updated every frame.
+#[derive(Default)]
+pub struct Lighting {
+    pub blockers: Blockers,
+    pub light_map: LightMap,
+    /// Whether the closed doors must be looked up again
+    pub doors_changed: bool,
+}
+
+impl Lighting {
+    pub fn new<M: Clone>(map: &TileMap<M>) -> Self {
+        Lighting {
+            blockers: Blockers::new(map),
+            light_map: LightMap::default(),
+            doors_changed: true,
+        }
+    }
+}
+
+/// Cells blocking light and sight: walls, darkness zones and closed doors.
+#[derive(Debug, Default)]
+pub struct Blockers {
+    walls: HashSet<Cell>,
+    darkness: HashSet<Cell>,
+    doors: HashSet<Cell>,
+}
+
+impl Blockers {
+    /// Blockers of a map, without the doors, which are entities.
+    pub fn new<M: Clone>(map: &TileMap<M>) -> Self {
+        Blockers {
+            walls: map.wall_cells(),
+            darkness: darkness_cells(map),
+            doors: HashSet::new(),
+        }
+    }
+
+    /// Replace the cells of the closed doors.
+    pub fn set_doors(&mut self, doors: impl IntoIterator<Item = Cell>) {
+        self.doors = doors.into_iter().collect();
+    }
+
+    pub fn blocks(&self, cell: &Cell) -> bool {
+        self.walls.contains(cell) || self.darkness.contains(cell) || self.doors.contains(cell)
+    }
+
+    pub fn is_dark(&self, cell: &Cell) -> bool {
+        self.darkness.contains(cell)
+    }
+}
+
+/// Cells declared with `:darkness x y [width height]`. Nothing can be
+/// lit or seen through them.
+pub fn darkness_cells<M: Clone>(map: &TileMap<M>) -> HashSet<Cell> {
+    let mut cells = HashSet::new();
+
+    for directive in map.directives("darkness") {
+        let Ok(origin) = directive.cell() else {
+            warn!("Invalid darkness zone at level {}", directive.level);
+            continue;
+        };
+
+        let size = |i: usize| {
+            directive
+                .params()
+                .get(i)
+                .and_then(|s| s.parse::<i32>().ok())
+                .unwrap_or(1)
+        };
+
+        for y in origin.y..origin.y + size(1) {
+            for x in origin.x..origin.x + size(0) {
+                cells.insert(Cell::new(x, y, origin.level));
+            }
+        }
+    }
+
+    cells
+}
+
+/// Light level of the cells around each light. A light reaches a cell if
+/// nothing blocks the line between them; contributions add up to 1.
+pub fn light_levels<M: Clone>(
+    map: &TileMap<M>,
+    blockers: &Blockers,
+    lights: &[(Vec3, LightSource)],
+) -> LightMap {
+    let mut levels: HashMap<Cell, f32> = HashMap::new();
+
+    for (position, light) in lights {
+        let origin = map.cell(*position);
+        let reach = light.radius.ceil() as i32;
+
+        for y in origin.y - reach..=origin.y + reach {
+            for x in origin.x - reach..=origin.x + reach {
+                let cell = Cell::new(x, y, origin.level);
+                let distance = Vec3::new((x - origin.x) as f32, 0., (y - origin.y) as f32).length();
+                let brightness = light.brightness(distance);
+
+                if brightness <= 0. || blockers.is_dark(&cell) {
+                    continue;
+                }
+
+                if line_of_sight(blockers, origin, cell) {
+                    let level = levels.entry(cell).or_insert(0.);
+                    *level = (*level + brightness).min(1.);
+                }
+            }
+        }
+    }
+
+    LightMap { levels }
+}
+
+/// True if no blocking cell lies strictly between two cells of the same
+/// level. The end cells themselves may be walls, so that lit walls are
+/// visible.
+pub fn line_of_sight(blockers: &Blockers, from: Cell, to: Cell) -> bool {
+    if from.level != to.level {
+        return false;
+    }
+
+    let (dx, dy) = ((to.x - from.x).abs(), -(to.y - from.y).abs());
+    let (sx, sy) = ((to.x - from.x).signum(), (to.y - from.y).signum());
+    let mut error = dx + dy;
+    let (mut x, mut y) = (from.x, from.y);
+
+    while (x, y) != (to.x, to.y) {
+        let e2 = 2 * error;
+        if e2 >= dy {
+            error += dy;
+            x += sx;
+        }
+        if e2 <= dx {
+            error += dx;
+            y += sy;
+        }
+
+        let cell = Cell::new(x, y, from.level);
+        if cell != to && blockers.blocks(&cell) {
+            return false;
+        }
+    }
+
+    true
+}
+
+/// Whether an observer notices a target. Perception reaches `range` cells
+/// in full light and shrinks in the dark, adjacent targets are always
+/// noticed unless hidden in a darkness zone.
+pub fn perceives(
+    light_map: &LightMap,
+    blockers: &Blockers,
+    observer: Cell,
+    range: f32,
+    target: Cell,
+) -> bool {
+    if blockers.is_dark(&target) || !line_of_sight(blockers, observer, target) {
+        return false;
+    }
+
+    let distance = Vec3::new(
+        (target.x - observer.x) as f32,
+        0.,
+        (target.y - observer.y) as f32,
+    )
+    .length();
+
+    distance <= 1. || distance <= range * light_map.level(target)
+}
+
+pub fn is_hidden(light_map: &LightMap, cell: Cell) -> bool {
+    light_map.level(cell) < HIDE_THRESHOLD
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::Flicker;
+
+    const MAP: &str = "wwwwwww\nw@....w\nw.w...w\nw.....w\nwwwwwww\n:darkness 4 3 2 1\n";
+
+    fn load() -> TileMap<()> {
+        let mut map = TileMap::new();
+        map.load(MAP, (), ()).unwrap();
+        map
+    }
+
+    fn light(map: &TileMap<()>, x: i32, y: i32) -> (Vec3, LightSource) {
+        let position = map.position(Cell::new(x, y, 0));
+        (position, LightSource::new(Vec3::ONE, 4., Flicker::Steady))
+    }
+
+    #[test]
+    fn light_fades_with_distance() {
+        let map = load();
+        let blockers = Blockers::new(&map);
+        let light_map = light_levels(&map, &blockers, &[light(&map, 1, 1)]);
+
+        assert_eq!(light_map.level(Cell::new(1, 1, 0)), 1.);
+        assert_eq!(light_map.level(Cell::new(3, 1, 0)), 0.5);
+        assert_eq!(light_map.level(Cell::new(5, 1, 0)), 0.);
+    }
+
+    #[test]
+    fn lights_add_up_to_one() {
+        let map = load();
+        let blockers = Blockers::new(&map);
+        let light_map = light_levels(&map, &blockers, &[light(&map, 1, 1), light(&map, 3, 1)]);
+
+        assert_eq!(light_map.level(Cell::new(2, 1, 0)), 1.);
+    }
+
+    #[test]
+    fn walls_and_darkness_block_light() {
+        let map = load();
+        let blockers = Blockers::new(&map);
+        let light_map = light_levels(&map, &blockers, &[light(&map, 3, 2)]);
+
+        // the wall itself is lit, not the cell behind it
+        assert!(light_map.level(Cell::new(2, 2, 0)) > 0.);
+        assert_eq!(light_map.level(Cell::new(1, 2, 0)), 0.);
+        assert_eq!(light_map.level(Cell::new(4, 3, 0)), 0.);
+    }
+
+    #[test]
+    fn line_of_sight_through_cells() {
+        let map = load();
+        let blockers = Blockers::new(&map);
+
+        assert!(line_of_sight(
+            &blockers,
+            Cell::new(1, 1, 0),
+            Cell::new(5, 1, 0)
+        ));
+        assert!(!line_of_sight(
+            &blockers,
+            Cell::new(1, 2, 0),
+            Cell::new(3, 2, 0)
+        ));
+        assert!(!line_of_sight(
+            &blockers,
+            Cell::new(3, 3, 0),
+            Cell::new(5, 3, 0)
+        ));
+        assert!(!line_of_sight(
+            &blockers,
+            Cell::new(1, 1, 0),
+            Cell::new(1, 1, 1)
+        ));
+    }
+
+    #[test]
+    fn closed_doors_block_sight_and_light() {
+        let map = load();
+        let mut blockers = Blockers::new(&map);
+        let (from, to) = (Cell::new(1, 1, 0), Cell::new(5, 1, 0));
+
+        blockers.set_doors([Cell::new(3, 1, 0)]);
+        assert!(!line_of_sight(&blockers, from, to));
+        let light_map = light_levels(&map, &blockers, &[light(&map, 1, 1)]);
+        assert_eq!(light_map.level(Cell::new(4, 1, 0)), 0.);
+
+        blockers.set_doors([]);
+        assert!(line_of_sight(&blockers, from, to));
+    }
+
+    #[test]
+    fn perception_shrinks_in_the_dark() {
+        let map = load();
+        let blockers = Blockers::new(&map);
+        let light_map = light_levels(&map, &blockers, &[light(&map, 5, 1)]);
+        let observer = Cell::new(1, 1, 0);
+
+        // lit
+        assert!(perceives(
+            &light_map,
+            &blockers,
+            observer,
+            6.,
+            Cell::new(5, 1, 0)
+        ));
+        // too dark for the distance
+        assert!(!perceives(
+            &light_map,
+            &blockers,
+            observer,
+            6.,
+            Cell::new(1, 3, 0)
+        ));
+        // adjacent
+        assert!(perceives(
+            &light_map,
+            &blockers,
+            observer,
+            6.,
+            Cell::new(1, 2, 0)
+        ));
+        // in a darkness zone
+        assert!(!perceives(
+            &light_map,
+            &blockers,
+            Cell::new(4, 2, 0),
+            6.,
+            Cell::new(4, 3, 0)
+        ));
+
+        assert!(is_hidden(&light_map, Cell::new(1, 3, 0)));
+        assert!(!is_hidden(&light_map, Cell::new(5, 1, 0)));
+    }
+}
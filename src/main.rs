@@ -1,10 +1,64 @@
+use std::{fs, path::PathBuf, process};
+
+use clap::Parser;
 use gobs::game::app::Application;
 
 use blobber;
 use blobber::app::App;
+use blobber::map::TileMap;
+use blobber::options::{Command, Options};
+use blobber::{headless, validation};
 
 fn main() {
-    blobber::init_logger();
+    let options = Options::parse();
+
+    blobber::init_logger(options.log_level);
+
+    if let Some(Command::Validate { map }) = &options.command {
+        process::exit(validate(map.as_ref()));
+    }
+
+    if let Some(ticks) = options.headless {
+        if let Err(e) = headless::run(&options, ticks) {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+        return;
+    }
+
+    let (width, height) = (options.width, options.height);
+
+    Options::set(options);
+
+    Application::new().with_size(width, height).run::<App>();
+}
+
+fn validate(path: Option<&PathBuf>) -> i32 {
+    let data = match path {
+        Some(path) => match fs::read_to_string(path) {
+            Ok(data) => data,
+            Err(e) => {
+                eprintln!("{}: {}", path.display(), e);
+                return 2;
+            }
+        },
+        None => blobber::MAP.to_string(),
+    };
+
+    let mut map = TileMap::new();
+    if let Err(e) = map.load(&data, (), ()) {
+        eprintln!("{}", e);
+        return 2;
+    }
+
+    let diagnostics = validation::validate(&map);
+    for diagnostic in &diagnostics {
+        println!("{}", diagnostic);
+    }
 
-    Application::new().run::<App>();
+    if validation::has_errors(&diagnostics) {
+        1
+    } else {
+        0
+    }
 }
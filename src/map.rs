@@ -1,7 +1,25 @@
-use anyhow::Result;
+use std::collections::HashSet;
+use std::fmt;
+
+use anyhow::{anyhow, Result};
 use glam::Vec3;
 use log::*;
 
+/// Line separating two levels in a map file. Levels are stacked, the first
+/// one being at the top.
+pub const LEVEL_SEPARATOR: &str = "---";
+
+/// Prefix of map file lines holding a directive rather than tiles.
+pub const DIRECTIVE_PREFIX: char = ':';
+
+/// Map positions are in tile units, the tile size is only applied when
+/// building the scene.
+const OFFSET: f32 = 16.;
+/// Vertical distance between two stacked levels.
+pub const LEVEL_HEIGHT: f32 = 2.;
+/// Depth of the floor tiles below walking height.
+const FLOOR_DEPTH: f32 = 1.;
+
 pub enum TileSet<M> {
     WALL(M),
     FLOOR(M),
@@ -21,8 +39,103 @@ pub struct Tile<M> {
     pub tile: TileSet<M>,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FeatureKind {
+    StairsUp,
+    StairsDown,
+    Spawn,
+    Door,
+    /// Door looking like a wall until found
+    SecretDoor,
+    /// Wall that can be walked through
+    Illusion,
+    /// Opening in the floor, over the cell below
+    Hole,
+    /// Hole with a ladder down to the cell below
+    Ladder,
+    /// Hole with a rope hanging down to the cell below
+    Rope,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Feature {
+    pub position: Vec3,
+    pub kind: FeatureKind,
+}
+
+/// Grid coordinates of a map cell: column and row in the map file, and
+/// level index.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Cell {
+    pub x: i32,
+    pub y: i32,
+    pub level: i32,
+}
+
+impl Cell {
+    pub fn new(x: i32, y: i32, level: i32) -> Self {
+        Cell { x, y, level }
+    }
+
+    pub fn neighbours(&self) -> [Cell; 4] {
+        [
+            Cell::new(self.x, self.y - 1, self.level),
+            Cell::new(self.x + 1, self.y, self.level),
+            Cell::new(self.x, self.y + 1, self.level),
+            Cell::new(self.x - 1, self.y, self.level),
+        ]
+    }
+
+    /// Cell at the same place on the level above.
+    pub fn above(&self) -> Cell {
+        Cell::new(self.x, self.y, self.level - 1)
+    }
+
+    /// Cell at the same place on the level below.
+    pub fn below(&self) -> Cell {
+        Cell::new(self.x, self.y, self.level + 1)
+    }
+}
+
+impl fmt::Display for Cell {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({}, {}) level {}", self.x, self.y, self.level)
+    }
+}
+
+/// Data attached to a level, written `:name x y args...` in the map file.
+/// Directives are interpreted by the systems using them.
+#[derive(Clone, Debug)]
+pub struct Directive {
+    pub name: String,
+    pub level: i32,
+    pub args: Vec<String>,
+}
+
+impl Directive {
+    /// Cell given by the first two arguments.
+    pub fn cell(&self) -> Result<Cell> {
+        let coordinate = |i: usize| -> Result<i32> {
+            self.args
+                .get(i)
+                .ok_or_else(|| anyhow!("{}: missing cell coordinates", self.name))?
+                .parse()
+                .map_err(|e| anyhow!("{}: invalid coordinate: {}", self.name, e))
+        };
+
+        Ok(Cell::new(coordinate(0)?, coordinate(1)?, self.level))
+    }
+
+    /// Arguments following the cell coordinates.
+    pub fn params(&self) -> &[String] {
+        self.args.get(2..).unwrap_or(&[])
+    }
+}
+
 pub struct TileMap<M> {
     pub tiles: Vec<Tile<M>>,
+    pub features: Vec<Feature>,
+    pub directives: Vec<Directive>,
     pub start: Vec3,
 }
 
@@ -30,6 +143,8 @@ impl<M: Clone> TileMap<M> {
     pub fn new() -> Self {
         TileMap {
             tiles: Vec::new(),
+            features: Vec::new(),
+            directives: Vec::new(),
             start: Vec3::new(0., 0., 0.),
         }
     }
@@ -40,58 +155,213 @@ impl<M: Clone> TileMap<M> {
         self.tiles.push(tile);
     }
 
+    pub fn add_feature(&mut self, kind: FeatureKind, position: Vec3) {
+        let feature = Feature { position, kind };
+
+        self.features.push(feature);
+    }
+
+    pub fn features(&self, kind: FeatureKind) -> impl Iterator<Item = &Feature> {
+        self.features.iter().filter(move |f| f.kind == kind)
+    }
+
+    pub fn directives<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Directive> {
+        self.directives.iter().filter(move |d| d.name == name)
+    }
+
+    pub fn feature_at(&self, kind: FeatureKind, cell: Cell) -> bool {
+        self.features(kind).any(|f| self.cell(f.position) == cell)
+    }
+
     pub fn collides(&self, position: Vec3) -> bool {
         self.tiles.iter().find(|t| t.position == position).is_some()
     }
 
-    pub fn load(&mut self, data: &str, tile_size: f32, wall_id: M, floor_id: M) -> Result<()> {
+    /// Cell containing a position at walking height (walls) or at floor
+    /// height.
+    pub fn cell(&self, position: Vec3) -> Cell {
+        Cell {
+            x: (position.x + OFFSET).round() as i32 - 1,
+            y: (position.z + OFFSET).round() as i32,
+            level: (-position.y / LEVEL_HEIGHT).floor() as i32,
+        }
+    }
+
+    /// Position of a cell at walking height.
+    pub fn position(&self, cell: Cell) -> Vec3 {
+        Vec3::new(
+            (cell.x + 1) as f32 - OFFSET,
+            -(cell.level as f32) * LEVEL_HEIGHT,
+            cell.y as f32 - OFFSET,
+        )
+    }
+
+    pub fn is_wall(&self, cell: Cell) -> bool {
+        let position = self.position(cell);
+
+        self.tiles
+            .iter()
+            .any(|t| matches!(t.tile, TileSet::WALL(_)) && t.position == position)
+    }
+
+    pub fn wall_cells(&self) -> HashSet<Cell> {
+        self.tiles
+            .iter()
+            .filter(|t| matches!(t.tile, TileSet::WALL(_)))
+            .map(|t| self.cell(t.position))
+            .collect()
+    }
+
+    /// Position of the floor tile of a cell.
+    pub fn floor_position(&self, cell: Cell) -> Vec3 {
+        self.position(cell) - Vec3::Y * FLOOR_DEPTH
+    }
+
+    pub fn is_floor(&self, cell: Cell) -> bool {
+        let position = self.floor_position(cell);
+
+        self.tiles
+            .iter()
+            .any(|t| matches!(t.tile, TileSet::FLOOR(_)) && t.position == position)
+    }
+
+    pub fn is_illusion(&self, cell: Cell) -> bool {
+        self.feature_at(FeatureKind::Illusion, cell)
+    }
+
+    pub fn is_walkable(&self, cell: Cell) -> bool {
+        self.is_floor(cell) && (!self.is_wall(cell) || self.is_illusion(cell))
+    }
+
+    /// Cell without floor, where anything not climbing or flying falls to
+    /// the level below.
+    pub fn is_open(&self, cell: Cell) -> bool {
+        self.feature_at(FeatureKind::Hole, cell) || self.is_climbable(cell)
+    }
+
+    pub fn is_climbable(&self, cell: Cell) -> bool {
+        self.feature_at(FeatureKind::Ladder, cell) || self.feature_at(FeatureKind::Rope, cell)
+    }
+
+    /// Whether a cell can be climbed to from the cell above or below it.
+    /// Ladders and ropes hang from their cell down to the cell below.
+    pub fn can_climb(&self, from: Cell, to: Cell) -> bool {
+        if to == from.above() {
+            self.is_climbable(to) && !self.is_wall(to)
+        } else if to == from.below() {
+            self.is_climbable(from) && (self.is_walkable(to) || self.is_open(to))
+        } else {
+            false
+        }
+    }
+
+    /// Arrival position on a level: the start if it is on that level, else
+    /// the up stairs, else the first walkable cell.
+    pub fn start_on(&self, level: i32) -> Option<Vec3> {
+        if self.cell(self.start).level == level {
+            return Some(self.start);
+        }
+
+        if let Some(stairs) = self
+            .features(FeatureKind::StairsUp)
+            .find(|f| self.cell(f.position).level == level)
+        {
+            return Some(stairs.position);
+        }
+
+        self.tiles
+            .iter()
+            .map(|t| self.cell(t.position))
+            .find(|&c| c.level == level && self.is_walkable(c))
+            .map(|c| self.position(c))
+    }
+
+    /// Lowest and highest column and row used by a level.
+    pub fn bounds(&self, level: i32) -> Option<(Cell, Cell)> {
+        let cells = self
+            .tiles
+            .iter()
+            .map(|t| self.cell(t.position))
+            .filter(|c| c.level == level);
+
+        cells.fold(None, |bounds, c| match bounds {
+            None => Some((c, c)),
+            Some((min, max)) => Some((
+                Cell::new(min.x.min(c.x), min.y.min(c.y), level),
+                Cell::new(max.x.max(c.x), max.y.max(c.y), level),
+            )),
+        })
+    }
+
+    pub fn load(&mut self, data: &str, wall_id: M, floor_id: M) -> Result<()> {
         info!("Load scene");
 
-        let offset = 16.;
+        let mut j = 0.;
+        let mut level = 0;
+        let mut level_y = 0.;
+
+        let (mut pos_x, mut pos_y, mut pos_z) = (0., 0., 0.);
+
+        for line in data.lines() {
+            if line.starts_with(LEVEL_SEPARATOR) {
+                j = 0.;
+                level += 1;
+                level_y -= LEVEL_HEIGHT;
+                continue;
+            }
+
+            if let Some(directive) = line.strip_prefix(DIRECTIVE_PREFIX) {
+                let mut words = directive.split_whitespace().map(String::from);
+                if let Some(name) = words.next() {
+                    self.directives.push(Directive {
+                        name,
+                        level,
+                        args: words.collect(),
+                    });
+                }
+                continue;
+            }
 
-        let (mut i, mut j) = (0., 0.);
+            let mut i = 0.;
 
-        let (mut pos_x, pos_y, mut pos_z) = (0., 0., 0.);
+            for c in line.chars() {
+                let position = match c {
+                    'w' | '@' | '.' | '<' | '>' | 'm' | '+' | 's' | 'i' | 'o' | 'h' | 'r' => {
+                        i += 1.;
+                        Vec3 {
+                            x: i - OFFSET,
+                            y: level_y,
+                            z: j - OFFSET,
+                        }
+                    }
+                    _ => continue,
+                };
 
-        for c in data.chars() {
-            match c {
-                'w' => {
-                    i += tile_size;
-                    let mut position = Vec3 {
-                        x: i - offset,
-                        y: 0.,
-                        z: j - offset,
-                    };
+                let floor = position - Vec3::Y * FLOOR_DEPTH;
 
+                if c == 'w' || c == 'i' {
                     self.add_tile(TileSet::WALL(wall_id.clone()), position);
-                    position.y = -crate::TILE_SIZE;
-                    self.add_tile(TileSet::FLOOR(floor_id.clone()), position);
-                }
-                '@' => {
-                    i += crate::TILE_SIZE;
-                    let position = Vec3 {
-                        x: i - offset,
-                        y: -crate::TILE_SIZE,
-                        z: j - offset,
-                    };
-                    (pos_x, pos_z) = (position.x, position.z);
-                    self.add_tile(TileSet::FLOOR(floor_id.clone()), position);
                 }
-                '.' => {
-                    i += crate::TILE_SIZE;
-                    let position = Vec3 {
-                        x: i - offset,
-                        y: -crate::TILE_SIZE,
-                        z: j - offset,
-                    };
-                    self.add_tile(TileSet::FLOOR(floor_id.clone()), position);
+                if !matches!(c, 'o' | 'h' | 'r') {
+                    self.add_tile(TileSet::FLOOR(floor_id.clone()), floor);
                 }
-                '\n' => {
-                    j += crate::TILE_SIZE;
-                    i = 0.;
+
+                match c {
+                    '@' => (pos_x, pos_y, pos_z) = (position.x, position.y, position.z),
+                    '<' => self.add_feature(FeatureKind::StairsUp, position),
+                    '>' => self.add_feature(FeatureKind::StairsDown, position),
+                    'm' => self.add_feature(FeatureKind::Spawn, position),
+                    '+' => self.add_feature(FeatureKind::Door, position),
+                    's' => self.add_feature(FeatureKind::SecretDoor, position),
+                    'i' => self.add_feature(FeatureKind::Illusion, position),
+                    'o' => self.add_feature(FeatureKind::Hole, position),
+                    'h' => self.add_feature(FeatureKind::Ladder, position),
+                    'r' => self.add_feature(FeatureKind::Rope, position),
+                    _ => (),
                 }
-                _ => (),
             }
+
+            j += 1.;
         }
 
         self.start = Vec3::new(pos_x, pos_y, pos_z);
@@ -1,52 +1,78 @@
-use std::f32::consts::FRAC_PI_2;
-use std::f32::consts::PI;
+use std::f32::consts::{FRAC_PI_2, FRAC_PI_4};
 
 use glam::Vec3;
+use serde::{Deserialize, Serialize};
 
-#[derive(Copy, Clone, Debug)]
+use crate::map::LEVEL_HEIGHT;
+
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize, Serialize)]
 pub enum Facing {
     North,
     South,
     East,
     West,
+    NorthEast,
+    SouthEast,
+    SouthWest,
+    NorthWest,
 }
 
 impl Facing {
+    pub const ALL: [Facing; 4] = [Facing::North, Facing::East, Facing::South, Facing::West];
+
+    /// All facings, clockwise from North, one eighth of a turn apart.
+    const CLOCKWISE: [Facing; 8] = [
+        Facing::North,
+        Facing::NorthEast,
+        Facing::East,
+        Facing::SouthEast,
+        Facing::South,
+        Facing::SouthWest,
+        Facing::West,
+        Facing::NorthWest,
+    ];
+
+    fn index(self) -> i32 {
+        Self::CLOCKWISE.iter().position(|f| *f == self).unwrap() as i32
+    }
+
     pub fn yaw(&self) -> f32 {
-        match self {
-            Facing::North => -FRAC_PI_2,
-            Facing::South => FRAC_PI_2,
-            Facing::East => 0.,
-            Facing::West => PI,
-        }
+        self.index() as f32 * FRAC_PI_4 - FRAC_PI_2
+    }
+
+    /// Facing closest to a yaw, among the cardinal directions or all eight.
+    pub fn from_yaw(yaw: f32, diagonals: bool) -> Self {
+        let step = if diagonals { 1 } else { 2 };
+        let eighths = ((yaw + FRAC_PI_2) / (FRAC_PI_4 * step as f32)).round() as i32 * step;
+
+        Facing::North.rotate(eighths)
+    }
+
+    pub fn is_diagonal(&self) -> bool {
+        self.index() % 2 == 1
+    }
+
+    /// Facing after turning by a number of eighths of a turn, clockwise.
+    pub fn rotate(self, eighths: i32) -> Self {
+        Self::CLOCKWISE[(self.index() + eighths).rem_euclid(8) as usize]
     }
 
+    /// Facing after a quarter turn.
     pub fn turn(self, direction: Direction) -> Self {
+        self.rotate(direction.eighths(2))
+    }
+
+    /// Unit step on the grid, diagonals moving along both axes.
+    fn step(&self) -> (f32, f32) {
         match self {
-            Facing::North => match direction {
-                Direction::Left => Facing::West,
-                Direction::Right => Facing::East,
-                Direction::Forward => Facing::North,
-                Direction::Backward => Facing::South,
-            },
-            Facing::South => match direction {
-                Direction::Left => Facing::East,
-                Direction::Right => Facing::West,
-                Direction::Forward => Facing::South,
-                Direction::Backward => Facing::North,
-            },
-            Facing::East => match direction {
-                Direction::Left => Facing::North,
-                Direction::Right => Facing::South,
-                Direction::Forward => Facing::East,
-                Direction::Backward => Facing::West,
-            },
-            Facing::West => match direction {
-                Direction::Left => Facing::South,
-                Direction::Right => Facing::North,
-                Direction::Forward => Facing::West,
-                Direction::Backward => Facing::East,
-            },
+            Facing::North => (0., -1.),
+            Facing::NorthEast => (1., -1.),
+            Facing::East => (1., 0.),
+            Facing::SouthEast => (1., 1.),
+            Facing::South => (0., 1.),
+            Facing::SouthWest => (-1., 1.),
+            Facing::West => (-1., 0.),
+            Facing::NorthWest => (-1., -1.),
         }
     }
 }
@@ -57,15 +83,37 @@ pub enum Direction {
     Right,
     Forward,
     Backward,
+    /// Climb or fly to the level above
+    Up,
+    /// Climb or fly to the level below
+    Down,
 }
 
-pub fn get_translation(facing: Facing, direction: Direction, amount: f32) -> Vec3 {
-    let move_direction = facing.turn(direction);
+impl Direction {
+    /// Rotation towards this direction, in eighths of a turn clockwise, for
+    /// a turn of the given size.
+    pub fn eighths(&self, turn: i32) -> i32 {
+        match self {
+            Direction::Left => -turn,
+            Direction::Right => turn,
+            Direction::Forward | Direction::Up | Direction::Down => 0,
+            Direction::Backward => 4,
+        }
+    }
 
-    match move_direction {
-        Facing::North => Vec3::new(0., 0., -amount),
-        Facing::South => Vec3::new(0., 0., amount),
-        Facing::East => Vec3::new(amount, 0., 0.),
-        Facing::West => Vec3::new(-amount, 0., 0.),
+    pub fn is_vertical(&self) -> bool {
+        matches!(self, Direction::Up | Direction::Down)
     }
 }
+
+pub fn get_translation(facing: Facing, direction: Direction, amount: f32) -> Vec3 {
+    match direction {
+        Direction::Up => return Vec3::Y * LEVEL_HEIGHT * amount,
+        Direction::Down => return -Vec3::Y * LEVEL_HEIGHT * amount,
+        _ => (),
+    }
+
+    let (x, z) = facing.turn(direction).step();
+
+    Vec3::new(x * amount, 0., z * amount)
+}
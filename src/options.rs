@@ -0,0 +1,171 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use anyhow::{anyhow, Result};
+use clap::{Parser, Subcommand, ValueEnum};
+use log::LevelFilter;
+
+use crate::generator::{Algorithm, Generator};
+use crate::watcher;
+
+const SAVE_FILE: &str = "blobber/save.toml";
+
+static OPTIONS: OnceLock<Options> = OnceLock::new();
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum GeneratorArg {
+    Rooms,
+    Bsp,
+    Caves,
+}
+
+impl From<GeneratorArg> for Algorithm {
+    fn from(arg: GeneratorArg) -> Self {
+        match arg {
+            GeneratorArg::Rooms => Algorithm::Rooms,
+            GeneratorArg::Bsp => Algorithm::Bsp,
+            GeneratorArg::Caves => Algorithm::Caves,
+        }
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Check a map file and print diagnostics
+    Validate {
+        /// Map file (defaults to the built-in dungeon)
+        map: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug, Parser)]
+#[command(version, about = "First person dungeon crawler")]
+pub struct Options {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Map file (defaults to the built-in dungeon)
+    #[arg(short, long)]
+    pub map: Option<PathBuf>,
+
+    /// Generate a random level instead of loading a map
+    #[arg(short, long, value_enum, conflicts_with = "map")]
+    pub generate: Option<GeneratorArg>,
+
+    /// Level to start on
+    #[arg(short, long, default_value_t = 0)]
+    pub level: usize,
+
+    /// Random seed
+    #[arg(short, long, default_value_t = 0)]
+    pub seed: u64,
+
+    /// Save file to load
+    #[arg(long)]
+    pub load: Option<PathBuf>,
+
+    /// Save file used by the save and load keys (defaults to the loaded
+    /// save, else blobber/save.toml in the user data directory). Headless
+    /// runs write it when they end.
+    #[arg(long)]
+    pub save: Option<PathBuf>,
+
+    /// Settings file (defaults to blobber/settings.toml in the user config
+    /// directory)
+    #[arg(short, long)]
+    pub config: Option<PathBuf>,
+
+    /// Override a setting, e.g. `--set camera.fov=90`
+    #[arg(long, value_name = "KEY=VALUE")]
+    pub set: Vec<String>,
+
+    /// Log level (off, error, warn, info, debug, trace)
+    #[arg(long, default_value_t = LevelFilter::Info)]
+    pub log_level: LevelFilter,
+
+    /// Window width
+    #[arg(long, default_value_t = 1920)]
+    pub width: u32,
+
+    /// Window height
+    #[arg(long, default_value_t = 1080)]
+    pub height: u32,
+
+    /// Enable the wireframe pass
+    #[arg(long)]
+    pub wireframe: bool,
+
+    /// Reload the map and assets when they change on disk
+    #[arg(short, long)]
+    pub watch: bool,
+
+    /// Run the given number of ticks without a window and print the state
+    #[arg(long, value_name = "TICKS")]
+    pub headless: Option<u32>,
+}
+
+impl Options {
+    /// Store the options parsed by `main` so that they can be read when the
+    /// application is created.
+    pub fn set(options: Options) {
+        let _ = OPTIONS.set(options);
+    }
+
+    pub fn get() -> &'static Options {
+        OPTIONS.get_or_init(|| Options::parse_from(["blobber"]))
+    }
+
+    pub fn map_data(&self) -> Result<String> {
+        if let Some(algorithm) = self.generate {
+            let mut generator = Generator::new(algorithm.into(), 32, 32, self.seed);
+            return Ok(generator.generate().to_map());
+        }
+
+        match self.map_path() {
+            Some(path) => {
+                fs::read_to_string(&path).map_err(|e| anyhow!("{}: {}", path.display(), e))
+            }
+            None => Ok(crate::MAP.to_string()),
+        }
+    }
+
+    /// File the map is read from. In watch mode the built-in dungeon is read
+    /// from the source assets so that it can be edited without recompiling.
+    pub fn map_path(&self) -> Option<PathBuf> {
+        if self.generate.is_some() {
+            return None;
+        }
+
+        match &self.map {
+            Some(path) => Some(path.clone()),
+            None if self.watch => Some(watcher::source_dir().join(crate::MAP_FILE)),
+            None => None,
+        }
+    }
+
+    /// File the save and load keys write and read.
+    pub fn save_path(&self) -> Option<PathBuf> {
+        self.save.clone().or_else(|| self.load.clone()).or_else(|| {
+            let dir = env::var_os("XDG_DATA_HOME")
+                .map(PathBuf::from)
+                .or_else(|| {
+                    env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share"))
+                })?;
+
+            Some(dir.join(SAVE_FILE))
+        })
+    }
+
+    /// Directory the map scripts are read from: the map directory, or the
+    /// assets for the built-in and generated maps, from the source tree in
+    /// watch mode.
+    pub fn script_dir(&self) -> PathBuf {
+        match self.map_path().as_deref().and_then(Path::parent) {
+            Some(dir) => dir.to_path_buf(),
+            None if self.watch => watcher::source_dir(),
+            None => crate::assets_dir(),
+        }
+    }
+}
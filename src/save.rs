@@ -0,0 +1,108 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use hecs::World;
+use serde::{Deserialize, Serialize};
+
+use crate::components::{Health, Orientation, Player, Position};
+use crate::movement::Facing;
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SaveGame {
+    pub position: [f32; 3],
+    pub facing: Facing,
+    pub health: Option<(u32, u32)>,
+}
+
+impl SaveGame {
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let data = fs::read_to_string(path).map_err(|e| anyhow!("{}: {}", path.display(), e))?;
+
+        toml::from_str(&data).map_err(|e| anyhow!("{}: {}", path.display(), e))
+    }
+
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| anyhow!("{}: {}", dir.display(), e))?;
+        }
+
+        fs::write(path, toml::to_string(self)?).map_err(|e| anyhow!("{}: {}", path.display(), e))
+    }
+
+    pub fn capture(world: &World) -> Option<Self> {
+        let mut query = world.query::<(&Player, &Position, &Orientation, Option<&Health>)>();
+
+        query
+            .iter()
+            .next()
+            .map(|(_, (_, position, orientation, health))| SaveGame {
+                position: [position.x, position.y, position.z],
+                facing: orientation.facing,
+                health: health.map(|h| (h.current, h.max)),
+            })
+    }
+
+    pub fn restore(&self, world: &mut World) {
+        world
+            .query_mut::<(
+                &Player,
+                &mut Position,
+                &mut Orientation,
+                Option<&mut Health>,
+            )>()
+            .into_iter()
+            .for_each(|(_, (_, position, orientation, health))| {
+                let [x, y, z] = self.position;
+                *position = Position { x, y, z };
+                orientation.face(self.facing);
+                if let (Some(health), Some((current, max))) = (health, self.health) {
+                    health.current = current;
+                    health.max = max;
+                }
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+
+    use super::*;
+    use crate::config::Settings;
+
+    #[test]
+    fn saved_party_is_restored() {
+        let mut world = World::new();
+        let player =
+            crate::spawner::spawn_player(&mut world, Vec3::new(1., 0., 2.), &Settings::default());
+        world.get::<&mut Health>(player).unwrap().current = 7;
+        world
+            .get::<&mut Orientation>(player)
+            .unwrap()
+            .face(Facing::East);
+
+        let path = std::env::temp_dir().join(format!("blobber-{}/save.toml", std::process::id()));
+        SaveGame::capture(&world).unwrap().write(&path).unwrap();
+
+        *world.get::<&mut Position>(player).unwrap() = Position::from(Vec3::ZERO);
+        world.get::<&mut Health>(player).unwrap().current = 20;
+        world
+            .get::<&mut Orientation>(player)
+            .unwrap()
+            .face(Facing::North);
+
+        SaveGame::read(&path).unwrap().restore(&mut world);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+
+        let position = *world.get::<&Position>(player).unwrap();
+        assert_eq!((position.x, position.y, position.z), (1., 0., 2.));
+        assert_eq!(
+            world.get::<&Orientation>(player).unwrap().facing,
+            Facing::East
+        );
+        assert_eq!(world.get::<&Health>(player).unwrap().current, 7);
+    }
+}
@@ -0,0 +1,231 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::rc::Rc;
+
+use anyhow::{anyhow, Result};
+use log::*;
+use rhai::{Dynamic, Engine, Scope, AST};
+
+use crate::map::{Cell, TileMap};
+
+/// Script hooks called by the script system.
+pub const ON_LEVEL_LOAD: &str = "on_level_load";
+pub const ON_ENTER_CELL: &str = "on_enter_cell";
+pub const ON_INTERACT: &str = "on_interact";
+pub const ON_TURN: &str = "on_turn";
+
+const MAX_OPERATIONS: u64 = 100_000;
+
+/// Changes requested by a script, applied to the world once the hook
+/// returns.
+#[derive(Clone, Debug)]
+pub enum Command {
+    Teleport(Cell),
+    Damage(u32),
+    Heal(u32),
+    Door(Cell, bool),
+    GiveItem(String),
+    TakeItem(String),
+    Spawn(Cell, String, u32),
+    Message(String),
+}
+
+/// Game state a hook can query, and the commands it issued.
+#[derive(Clone, Debug, Default)]
+pub struct Context {
+    pub cell: Option<Cell>,
+    pub facing: String,
+    pub health: (u32, u32),
+    pub items: Vec<String>,
+    pub doors: HashMap<Cell, bool>,
+    pub commands: Vec<Command>,
+}
+
+/// Rhai scripts attached to a map with `:script FILE`. Scripts only reach
+/// the game through the functions registered here.
+#[derive(Default)]
+pub struct Scripts {
+    engine: Option<Engine>,
+    ast: Option<AST>,
+    scope: Scope<'static>,
+    context: Rc<RefCell<Context>>,
+    /// Level the last `on_level_load` was called for
+    pub level: Option<i32>,
+}
+
+impl Scripts {
+    /// Compile the scripts declared in a map, file names being relative to
+    /// `dir`.
+    pub fn load<M: Clone>(map: &TileMap<M>, dir: &Path) -> Result<Self> {
+        let mut scripts = Scripts::default();
+
+        let mut source = String::new();
+        for directive in map.directives("script") {
+            let file = directive
+                .args
+                .first()
+                .ok_or_else(|| anyhow!("script: missing file name"))?;
+            source.push_str(&fs::read_to_string(dir.join(file))?);
+            source.push('\n');
+        }
+
+        if source.is_empty() {
+            return Ok(scripts);
+        }
+
+        let engine = Self::engine(scripts.context.clone());
+        let ast = engine.compile(&source)?;
+
+        // Run the top level statements once so that they can set globals
+        engine
+            .run_ast_with_scope(&mut scripts.scope, &ast)
+            .map_err(|e| anyhow!("{}", e))?;
+
+        scripts.engine = Some(engine);
+        scripts.ast = Some(ast);
+
+        Ok(scripts)
+    }
+
+    /// Call a hook with the given game state. Returns the value returned by
+    /// the hook, or None if the hook is not defined or failed, and the
+    /// commands issued.
+    pub fn call(
+        &mut self,
+        hook: &str,
+        context: Context,
+        args: impl rhai::FuncArgs,
+    ) -> (Option<Dynamic>, Vec<Command>) {
+        let (Some(engine), Some(ast)) = (&self.engine, &self.ast) else {
+            return (None, Vec::new());
+        };
+
+        if !ast.iter_functions().any(|f| f.name == hook) {
+            return (None, Vec::new());
+        }
+
+        *self.context.borrow_mut() = context;
+
+        let result = match engine.call_fn::<Dynamic>(&mut self.scope, ast, hook, args) {
+            Ok(value) => Some(value),
+            Err(e) => {
+                error!("Script {}: {}", hook, e);
+                None
+            }
+        };
+
+        let commands = std::mem::take(&mut self.context.borrow_mut().commands);
+
+        (result, commands)
+    }
+
+    fn engine(context: Rc<RefCell<Context>>) -> Engine {
+        let mut engine = Engine::new();
+
+        engine.set_max_operations(MAX_OPERATIONS);
+        engine.set_max_call_levels(32);
+        engine.on_print(|text| info!("Script: {}", text));
+        engine.on_debug(|text, _, pos| debug!("Script {}: {}", pos, text));
+
+        let ctx = context.clone();
+        engine.register_fn("player", move || {
+            let context = ctx.borrow();
+            let mut player = rhai::Map::new();
+            if let Some(cell) = context.cell {
+                player.insert("x".into(), (cell.x as i64).into());
+                player.insert("y".into(), (cell.y as i64).into());
+                player.insert("level".into(), (cell.level as i64).into());
+            }
+            player.insert("facing".into(), context.facing.clone().into());
+            player.insert("health".into(), (context.health.0 as i64).into());
+            player.insert("max_health".into(), (context.health.1 as i64).into());
+            player
+        });
+
+        let ctx = context.clone();
+        engine.register_fn("has_item", move |item: &str| {
+            ctx.borrow().items.iter().any(|i| i == item)
+        });
+
+        let ctx = context.clone();
+        engine.register_fn("door_open", move |x: i64, y: i64| {
+            let context = ctx.borrow();
+            let cell = cell_on_level(&context, x, y);
+            context.doors.get(&cell).copied().unwrap_or(false)
+        });
+
+        let command = |context: &Rc<RefCell<Context>>, command: Command| {
+            context.borrow_mut().commands.push(command);
+        };
+
+        let ctx = context.clone();
+        engine.register_fn("teleport", move |x: i64, y: i64| {
+            let cell = cell_on_level(&ctx.borrow(), x, y);
+            command(&ctx, Command::Teleport(cell));
+        });
+
+        let ctx = context.clone();
+        engine.register_fn("teleport", move |x: i64, y: i64, level: i64| {
+            command(
+                &ctx,
+                Command::Teleport(Cell::new(x as i32, y as i32, level as i32)),
+            );
+        });
+
+        let ctx = context.clone();
+        engine.register_fn("damage", move |amount: i64| {
+            command(&ctx, Command::Damage(amount.max(0) as u32));
+        });
+
+        let ctx = context.clone();
+        engine.register_fn("heal", move |amount: i64| {
+            command(&ctx, Command::Heal(amount.max(0) as u32));
+        });
+
+        let ctx = context.clone();
+        engine.register_fn("open_door", move |x: i64, y: i64| {
+            let cell = cell_on_level(&ctx.borrow(), x, y);
+            command(&ctx, Command::Door(cell, true));
+        });
+
+        let ctx = context.clone();
+        engine.register_fn("close_door", move |x: i64, y: i64| {
+            let cell = cell_on_level(&ctx.borrow(), x, y);
+            command(&ctx, Command::Door(cell, false));
+        });
+
+        let ctx = context.clone();
+        engine.register_fn("give_item", move |item: &str| {
+            command(&ctx, Command::GiveItem(item.to_string()));
+        });
+
+        let ctx = context.clone();
+        engine.register_fn("take_item", move |item: &str| {
+            command(&ctx, Command::TakeItem(item.to_string()));
+        });
+
+        let ctx = context.clone();
+        engine.register_fn("spawn", move |x: i64, y: i64, name: &str, health: i64| {
+            let cell = cell_on_level(&ctx.borrow(), x, y);
+            command(
+                &ctx,
+                Command::Spawn(cell, name.to_string(), health.max(1) as u32),
+            );
+        });
+
+        let ctx = context;
+        engine.register_fn("message", move |text: &str| {
+            command(&ctx, Command::Message(text.to_string()));
+        });
+
+        engine
+    }
+}
+
+fn cell_on_level(context: &Context, x: i64, y: i64) -> Cell {
+    let level = context.cell.map(|c| c.level).unwrap_or(0);
+
+    Cell::new(x as i32, y as i32, level)
+}
@@ -0,0 +1,427 @@
+use anyhow::{anyhow, Result};
+use glam::Vec3;
+use hecs::{Entity, World};
+use log::*;
+
+use crate::components::{
+    self, Door, DoorOperation, Flicker, FreeMovement, Fuel, Hazard, HazardKind, Health, Inventory,
+    Item, LightLevel, LightSource, Mana, Monster, Name, Orientation, Player, Position, Projectile,
+    ProjectileKind, Secret, Skills, Spellbook, StatusEffects, Trap, TrapKind, Trigger,
+    TriggerAction, TriggerKind,
+};
+use crate::config::Settings;
+use crate::map::{Cell, Directive, FeatureKind, TileMap};
+use crate::movement::Facing;
+
+/// Marker for entities created from map directives, removed when the map
+/// is reloaded.
+pub struct MapEntity;
+
+pub fn spawn_map_entities<M: Clone>(world: &mut World, map: &TileMap<M>) {
+    spawn_lights(world, map);
+    spawn_traps(world, map);
+    spawn_hazards(world, map);
+    spawn_doors(world, map);
+    spawn_triggers(world, map);
+    spawn_items(world, map);
+}
+
+pub fn despawn_map_entities(world: &mut World) {
+    let entities: Vec<Entity> = world.query::<&MapEntity>().iter().map(|(e, _)| e).collect();
+
+    for e in entities {
+        let _ = world.despawn(e);
+    }
+}
+
+pub fn spawn_player(world: &mut World, position: Vec3, settings: &Settings) -> Entity {
+    let mut torch = LightSource::torch();
+    torch.radius = settings.light.torch_radius;
+
+    let player = world.spawn((
+        Name { name: "Bob".into() },
+        Player,
+        components::Camera::new(),
+        Position::from(position),
+        Orientation::new(Facing::North).with_diagonals(settings.movement.diagonal),
+        torch,
+        Fuel::new(settings.light.torch_duration),
+        LightLevel::default(),
+        Health::new(20),
+        Skills {
+            perception: 2,
+            disarm: 2,
+        },
+        Inventory {
+            items: starting_items(),
+        },
+        Mana::new(20),
+        StatusEffects::default(),
+        Spellbook::new(
+            settings
+                .magic
+                .spells
+                .iter()
+                .map(|spell| spell.name.clone())
+                .collect(),
+        ),
+    ));
+
+    if settings.movement.free {
+        let _ = world.insert_one(player, FreeMovement::default());
+    }
+
+    player
+}
+
+fn starting_items() -> Vec<String> {
+    let mut items = vec!["bow".to_string()];
+    items.extend(vec!["arrow".to_string(); 12]);
+    items.extend(vec!["dagger".to_string(); 3]);
+    items
+}
+
+pub fn spawn_projectile(
+    world: &mut World,
+    position: Vec3,
+    facing: Facing,
+    kind: ProjectileKind,
+    owner: Entity,
+) -> Entity {
+    let projectile = Projectile {
+        kind,
+        owner,
+        range: kind.range(),
+    };
+
+    let entity = world.spawn((
+        Position::from(position),
+        Orientation::new(facing),
+        projectile,
+    ));
+
+    if kind == ProjectileKind::Fireball {
+        let light = LightSource::new(Vec3::new(1., 0.45, 0.1), 3., Flicker::Torch);
+        let _ = world.insert_one(entity, light);
+    }
+
+    entity
+}
+
+pub fn spawn_monster(world: &mut World, position: Vec3, name: &str, health: u32) -> Entity {
+    world.spawn((
+        Name { name: name.into() },
+        Monster,
+        Position::from(position),
+        Orientation::new(Facing::North),
+        Health::new(health),
+        StatusEffects::default(),
+        MapEntity,
+    ))
+}
+
+/// Spawn the light sources declared in the map with
+/// `:light x y torch|crystal` or `:light x y r g b radius [flicker]`.
+pub fn spawn_lights<M: Clone>(world: &mut World, map: &TileMap<M>) {
+    for directive in map.directives("light") {
+        match parse_light(directive) {
+            Ok(light) => {
+                let position = map.position(directive.cell().unwrap());
+                world.spawn((Position::from(position), light, MapEntity));
+            }
+            Err(e) => warn!("Invalid light at level {}: {}", directive.level, e),
+        }
+    }
+}
+
+fn parse_light(directive: &Directive) -> Result<LightSource> {
+    directive.cell()?;
+
+    let params = directive.params();
+
+    match params {
+        [preset] if preset == "torch" => Ok(LightSource::torch()),
+        [preset] if preset == "crystal" => Ok(LightSource::crystal()),
+        [r, g, b, radius, flicker @ ..] => {
+            let flicker = match flicker.first() {
+                Some(flicker) => flicker.parse()?,
+                None => Flicker::Steady,
+            };
+            Ok(LightSource::new(
+                Vec3::new(r.parse()?, g.parse()?, b.parse()?),
+                radius.parse()?,
+                flicker,
+            ))
+        }
+        _ => Err(anyhow!("expected a preset or colour and radius")),
+    }
+}
+
+/// Spawn the traps declared in the map with
+/// `:trap x y pit|spikes|darts|teleport [tx ty] [hidden] [difficulty]`.
+pub fn spawn_traps<M: Clone>(world: &mut World, map: &TileMap<M>) {
+    for directive in map.directives("trap") {
+        match parse_trap(map, directive) {
+            Ok((position, trap)) => {
+                world.spawn((Position::from(position), trap, MapEntity));
+            }
+            Err(e) => warn!("Invalid trap at level {}: {}", directive.level, e),
+        }
+    }
+}
+
+fn parse_trap<M: Clone>(map: &TileMap<M>, directive: &Directive) -> Result<(Vec3, Trap)> {
+    let cell = directive.cell()?;
+
+    let mut params = directive.params().iter();
+
+    let kind = match params.next().map(String::as_str) {
+        Some("pit") => TrapKind::Pit,
+        Some("spikes") => TrapKind::Spikes,
+        Some("darts") => TrapKind::Darts,
+        Some("teleport") => {
+            let mut coordinate = || -> Result<i32> {
+                Ok(params
+                    .next()
+                    .ok_or_else(|| anyhow!("missing teleport target"))?
+                    .parse()?)
+            };
+            let target = Cell::new(coordinate()?, coordinate()?, cell.level);
+            TrapKind::Teleport(map.position(target))
+        }
+        kind => return Err(anyhow!("unknown trap {:?}", kind)),
+    };
+
+    let mut trap = Trap {
+        kind,
+        hidden: false,
+        armed: true,
+        difficulty: 10,
+    };
+
+    for param in params {
+        match param.as_str() {
+            "hidden" => trap.hidden = true,
+            difficulty => trap.difficulty = difficulty.parse()?,
+        }
+    }
+
+    Ok((map.position(cell), trap))
+}
+
+/// Spawn the hazards declared in the map: `:spinner x y`, `:pit x y`,
+/// `:teleporter x y tx ty [level]` and `:antimagic x y [width height]`.
+pub fn spawn_hazards<M: Clone>(world: &mut World, map: &TileMap<M>) {
+    for name in ["spinner", "pit", "teleporter", "antimagic"] {
+        for directive in map.directives(name) {
+            match parse_hazard(map, directive) {
+                Ok(hazards) => {
+                    for (position, hazard) in hazards {
+                        world.spawn((Position::from(position), hazard, MapEntity));
+                    }
+                }
+                Err(e) => warn!("Invalid {} at level {}: {}", name, directive.level, e),
+            }
+        }
+    }
+}
+
+fn parse_hazard<M: Clone>(map: &TileMap<M>, directive: &Directive) -> Result<Vec<(Vec3, Hazard)>> {
+    let cell = directive.cell()?;
+
+    let params: Vec<i32> = directive
+        .params()
+        .iter()
+        .map(|p| p.parse())
+        .collect::<Result<_, _>>()?;
+
+    let hazard = |kind| Hazard { kind };
+
+    match (directive.name.as_str(), params.as_slice()) {
+        ("spinner", []) => Ok(vec![(map.position(cell), hazard(HazardKind::Spinner))]),
+        ("pit", []) => Ok(vec![(map.position(cell), hazard(HazardKind::Pit))]),
+        ("teleporter", [x, y, level @ ..]) => {
+            let level = level.first().copied().unwrap_or(cell.level);
+            let target = map.position(Cell::new(*x, *y, level));
+            Ok(vec![(
+                map.position(cell),
+                hazard(HazardKind::Teleporter(target)),
+            )])
+        }
+        ("antimagic", size) => {
+            let (width, height) = match size {
+                [] => (1, 1),
+                [width, height] => (*width, *height),
+                _ => return Err(anyhow!("expected width and height")),
+            };
+            let mut hazards = Vec::new();
+            for y in cell.y..cell.y + height {
+                for x in cell.x..cell.x + width {
+                    let position = map.position(Cell::new(x, y, cell.level));
+                    hazards.push((position, hazard(HazardKind::AntiMagic)));
+                }
+            }
+            Ok(hazards)
+        }
+        _ => Err(anyhow!("invalid parameters")),
+    }
+}
+
+/// Spawn a closed door on every `+` and `s` cell. `:door x y locked` locks
+/// the door of a cell and `:secret x y difficulty` sets how hard a secret
+/// door is to find.
+pub fn spawn_doors<M: Clone>(world: &mut World, map: &TileMap<M>) {
+    let locked: Vec<Cell> = map
+        .directives("door")
+        .filter_map(|directive| match (directive.cell(), directive.params()) {
+            (Ok(cell), [param]) if param == "locked" => Some(cell),
+            _ => {
+                warn!("Invalid door at level {}", directive.level);
+                None
+            }
+        })
+        .collect();
+
+    let difficulties: Vec<(Cell, u32)> = map
+        .directives("secret")
+        .filter_map(|directive| {
+            let difficulty = match directive.params() {
+                [difficulty] => difficulty.parse().ok(),
+                _ => None,
+            };
+            match (directive.cell(), difficulty) {
+                (Ok(cell), Some(difficulty)) => Some((cell, difficulty)),
+                _ => {
+                    warn!("Invalid secret door at level {}", directive.level);
+                    None
+                }
+            }
+        })
+        .collect();
+
+    for feature in map.features(FeatureKind::Door) {
+        let door = Door {
+            open: false,
+            locked: locked.contains(&map.cell(feature.position)),
+        };
+        world.spawn((Position::from(feature.position), door, MapEntity));
+    }
+
+    for feature in map.features(FeatureKind::SecretDoor) {
+        let cell = map.cell(feature.position);
+        let difficulty = difficulties
+            .iter()
+            .find(|(c, _)| *c == cell)
+            .map(|(_, difficulty)| *difficulty)
+            .unwrap_or(12);
+        world.spawn((
+            Position::from(feature.position),
+            Door::default(),
+            Secret { difficulty },
+            MapEntity,
+        ));
+    }
+}
+
+/// Spawn the triggers declared in the map with
+/// `:trigger x y lever|button|plate [once] [item=NAME] [group=NAME] -> ACTION; ...`
+/// where actions are `door x y [open|close|toggle]`, `spawn x y NAME [health]`
+/// or `message TEXT`.
+pub fn spawn_triggers<M: Clone>(world: &mut World, map: &TileMap<M>) {
+    for directive in map.directives("trigger") {
+        match parse_trigger(map, directive) {
+            Ok((position, trigger)) => {
+                world.spawn((Position::from(position), trigger, MapEntity));
+            }
+            Err(e) => warn!("Invalid trigger at level {}: {}", directive.level, e),
+        }
+    }
+}
+
+fn parse_trigger<M: Clone>(map: &TileMap<M>, directive: &Directive) -> Result<(Vec3, Trigger)> {
+    let cell = directive.cell()?;
+
+    let params = directive.params().join(" ");
+    let (switch, actions) = params
+        .split_once("->")
+        .ok_or_else(|| anyhow!("missing actions"))?;
+
+    let mut words = switch.split_whitespace();
+
+    let mut trigger = Trigger::new(match words.next() {
+        Some("lever") => TriggerKind::Lever,
+        Some("button") => TriggerKind::Button,
+        Some("plate") => TriggerKind::Plate,
+        kind => return Err(anyhow!("unknown trigger {:?}", kind)),
+    });
+
+    for word in words {
+        match word.split_once('=') {
+            None if word == "once" => trigger.once = true,
+            Some(("item", item)) => trigger.requires = Some(item.to_string()),
+            Some(("group", group)) => trigger.group = Some(group.to_string()),
+            _ => return Err(anyhow!("unknown option {}", word)),
+        }
+    }
+
+    for action in actions.split(';').map(str::trim).filter(|a| !a.is_empty()) {
+        trigger
+            .actions
+            .push(parse_trigger_action(map, cell.level, action)?);
+    }
+
+    Ok((map.position(cell), trigger))
+}
+
+fn parse_trigger_action<M: Clone>(
+    map: &TileMap<M>,
+    level: i32,
+    action: &str,
+) -> Result<TriggerAction> {
+    let words: Vec<&str> = action.split_whitespace().collect();
+
+    let target = |x: &str, y: &str| -> Result<Vec3> {
+        Ok(map.position(Cell::new(x.parse()?, y.parse()?, level)))
+    };
+
+    match words.as_slice() {
+        ["door", x, y, operation @ ..] => {
+            let operation = match operation {
+                [] | ["toggle"] => DoorOperation::Toggle,
+                ["open"] => DoorOperation::Open,
+                ["close"] => DoorOperation::Close,
+                _ => return Err(anyhow!("unknown door operation {:?}", operation)),
+            };
+            Ok(TriggerAction::Door(target(x, y)?, operation))
+        }
+        ["spawn", x, y, name, health @ ..] => {
+            let health = match health {
+                [] => 10,
+                [health] => health.parse()?,
+                _ => return Err(anyhow!("too many spawn parameters")),
+            };
+            Ok(TriggerAction::Spawn(
+                target(x, y)?,
+                name.to_string(),
+                health,
+            ))
+        }
+        ["message", ..] => Ok(TriggerAction::Message(
+            action["message".len()..].trim().to_string(),
+        )),
+        _ => Err(anyhow!("unknown action {:?}", action)),
+    }
+}
+
+/// Spawn the items lying on the floor, declared with `:item x y NAME`.
+pub fn spawn_items<M: Clone>(world: &mut World, map: &TileMap<M>) {
+    for directive in map.directives("item") {
+        match (directive.cell(), directive.params()) {
+            (Ok(cell), [name]) => {
+                let item = Item { name: name.clone() };
+                world.spawn((Position::from(map.position(cell)), item, MapEntity));
+            }
+            _ => warn!("Invalid item at level {}", directive.level),
+        }
+    }
+}
@@ -0,0 +1,147 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::components::{ProjectileKind, StatusEffect};
+use crate::map::Cell;
+use crate::movement::{self, Direction, Facing};
+
+/// Cells affected by a spell.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Area {
+    /// The caster only
+    Caster,
+    /// The target cell
+    Single,
+    /// Every cell between the caster and the target
+    Line,
+    /// Cells ahead of the caster, widening with the distance
+    Cone,
+    /// 3x3 cells around the target
+    Square,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Effect {
+    Damage(u32),
+    Heal(u32),
+    /// Light the target for a number of seconds
+    Light(f32),
+    OpenDoor,
+    /// Move the caster to the target
+    Teleport,
+    Status(StatusEffect),
+    /// Launch a projectile ahead of the caster
+    Projectile(ProjectileKind),
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Spell {
+    pub name: String,
+    /// Mana spent when casting
+    pub cost: u32,
+    /// Distance to the target, in tiles
+    pub range: u32,
+    pub area: Area,
+    /// Seconds before the spell can be cast again
+    #[serde(default)]
+    pub cooldown: f32,
+    pub effects: Vec<Effect>,
+}
+
+#[derive(Deserialize)]
+struct SpellList {
+    spells: Vec<Spell>,
+}
+
+/// Parse a spell list, written as `[[spells]]` tables.
+pub fn parse(data: &str) -> Result<Vec<Spell>> {
+    Ok(toml::from_str::<SpellList>(data)?.spells)
+}
+
+/// Spells shipped with the game.
+pub fn builtin() -> Vec<Spell> {
+    parse(crate::SPELLS).expect("Invalid built-in spells")
+}
+
+/// Step from a cell in a direction relative to a facing.
+pub fn step(cell: Cell, facing: Facing, direction: Direction, distance: i32) -> Cell {
+    let translation = movement::get_translation(facing, direction, 1.);
+
+    Cell::new(
+        cell.x + translation.x.round() as i32 * distance,
+        cell.y + translation.z.round() as i32 * distance,
+        cell.level,
+    )
+}
+
+/// Cells hit by a spell cast from `caster` towards `target`, `distance`
+/// being the number of cells between them. Only spells cast on the caster
+/// hit its cell.
+pub fn cells(area: Area, caster: Cell, facing: Facing, target: Cell, distance: i32) -> Vec<Cell> {
+    let cells: Vec<Cell> = match area {
+        Area::Caster => return vec![caster],
+        Area::Single => vec![target],
+        Area::Line => (1..=distance)
+            .map(|d| step(caster, facing, Direction::Forward, d))
+            .collect(),
+        Area::Cone => (1..=distance)
+            .flat_map(|d| {
+                let centre = step(caster, facing, Direction::Forward, d);
+                (1 - d..d).map(move |side| step(centre, facing, Direction::Right, side))
+            })
+            .collect(),
+        Area::Square => (-1..=1)
+            .flat_map(|y| {
+                (-1..=1).map(move |x| Cell::new(target.x + x, target.y + y, target.level))
+            })
+            .collect(),
+    };
+
+    cells.into_iter().filter(|c| *c != caster).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CASTER: Cell = Cell {
+        x: 5,
+        y: 5,
+        level: 0,
+    };
+
+    #[test]
+    fn square_next_to_the_caster() {
+        let target = Cell::new(5, 4, 0);
+        let cells = cells(Area::Square, CASTER, Facing::North, target, 1);
+
+        assert_eq!(cells.len(), 8);
+        assert!(!cells.contains(&CASTER));
+    }
+
+    #[test]
+    fn cone_widens_ahead() {
+        let cells = cells(Area::Cone, CASTER, Facing::North, CASTER, 2);
+
+        assert_eq!(
+            cells,
+            vec![
+                Cell::new(5, 4, 0),
+                Cell::new(4, 3, 0),
+                Cell::new(5, 3, 0),
+                Cell::new(6, 3, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn only_self_spells_hit_the_caster() {
+        assert_eq!(
+            cells(Area::Caster, CASTER, Facing::North, CASTER, 0),
+            vec![CASTER]
+        );
+        assert!(cells(Area::Single, CASTER, Facing::North, CASTER, 0).is_empty());
+    }
+}
@@ -1,29 +1,154 @@
 mod animate;
+mod awareness;
 mod camera;
 mod cleanup;
 mod collider;
+mod death;
+mod free_move;
+mod hazard;
 mod input;
+mod item;
+mod light;
 mod mover;
+mod projectile;
+mod script;
+mod search;
+mod spell;
+mod status;
+mod trap;
+mod trigger;
 
 use std::sync::Arc;
 
-use hecs::World;
+use hecs::{Entity, World};
+use rand::rngs::StdRng;
 
 use gobs::scene::{Model, Scene};
 
-use crate::{events::Event, map::TileMap};
+use crate::{
+    config::Settings, events::Event, lighting::Lighting, map::TileMap, scripting::Scripts,
+};
 
+/// Moves in a row caused by the cells entities land on.
+const FORCED_MOVES: usize = 4;
+
+#[allow(clippy::too_many_arguments)]
 pub fn update(
     delta: f32,
     world: &mut World,
-    events: &Vec<Event>,
+    events: &mut Vec<Event>,
     map: &TileMap<Arc<Model>>,
     scene: &mut Scene,
+    settings: &Settings,
+    rng: &mut StdRng,
+    scripts: &mut Scripts,
+    lighting: &mut Lighting,
 ) {
-    input::input_system(world, events, delta);
-    collider::collide_system(world, map);
-    animate::animate_system(world);
-    mover::move_system(world);
-    camera::camera_system(world, scene);
+    simulate(delta, world, events, map, settings, rng, scripts, lighting);
+    camera::update_scene(world, scene, settings);
+    light::update_scene(world, scene, settings);
+}
+
+/// Run the game logic without touching the scene.
+#[allow(clippy::too_many_arguments)]
+pub fn simulate<M: Clone>(
+    delta: f32,
+    world: &mut World,
+    events: &mut Vec<Event>,
+    map: &TileMap<M>,
+    settings: &Settings,
+    rng: &mut StdRng,
+    scripts: &mut Scripts,
+    lighting: &mut Lighting,
+) {
+    input::input_system(world, events, delta, settings);
+    status::paralysis_system(world, events);
+    projectile::projectile_system(world, events, map);
+    free_move::free_move_system(world, events, map, settings, delta);
+    collider::collide_system(world, events, map, settings);
+    animate::animate_system(world, settings);
+    mover::move_system(world, events);
+    trap::disarm_system(world, events, map, rng);
+    enter_cells(world, events, map, rng, 0);
+    item::pickup_system(world, events, map);
+    trigger::trigger_system(world, events, map);
+    search::search_system(world, events, map, rng);
+    awareness::awareness_system(world, events, map, lighting);
+    let cast = events.len();
+    spell::spell_system(world, events, map, settings, delta);
+    enter_cells(world, events, map, rng, cast);
+    status::status_system(world, events, delta);
+    death::death_system(world, events);
+    let scripted = events.len();
+    script::script_system(world, events, map, scripts);
+    enter_cells(world, events, map, rng, scripted);
+    camera::camera_system(world, events, settings, delta);
+    light::light_system(world, events, map, lighting, delta);
     cleanup::cleanup_system(world);
 }
+
+/// Apply the traps and effects of the cells entities moved to, from the
+/// events after `first`. Teleports and falls move them again, so the cells
+/// they land on are applied in turn, a few times at most to stop
+/// teleporters sending each other back.
+fn enter_cells<M: Clone>(
+    world: &mut World,
+    events: &mut Vec<Event>,
+    map: &TileMap<M>,
+    rng: &mut StdRng,
+    mut first: usize,
+) {
+    for round in 0..FORCED_MOVES {
+        // An entity moved twice in a round enters its last cell once
+        let mut moved: Vec<Entity> = Vec::new();
+        for event in &events[first..] {
+            if let Event::Moved(entity) = event {
+                if !moved.contains(entity) {
+                    moved.push(*entity);
+                }
+            }
+        }
+
+        if round > 0 && moved.is_empty() {
+            break;
+        }
+        first = events.len();
+
+        trap::trap_system(world, events, map, rng, &moved);
+        hazard::hazard_system(world, events, map, rng, &moved);
+        hazard::fall_system(world, events, map);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::map::Cell;
+
+    #[test]
+    fn teleport_onto_a_trap_hits_once() {
+        let mut map = TileMap::new();
+        map.load("wwwww\nw@..w\nwwwww\n:trap 3 1 spikes\n", (), ())
+            .unwrap();
+        let settings = Settings::default();
+        let mut world = World::new();
+        let player = crate::spawner::spawn_player(&mut world, map.start, &settings);
+        crate::spawner::spawn_map_entities(&mut world, &map);
+        let mut rng = StdRng::seed_from_u64(1);
+
+        // Moved onto the trap, reported by two teleports in the same round
+        *world
+            .get::<&mut crate::components::Position>(player)
+            .unwrap() = map.position(Cell::new(3, 1, 0)).into();
+        let mut events = vec![Event::Moved(player), Event::Moved(player)];
+        enter_cells(&mut world, &mut events, &map, &mut rng, 0);
+
+        let hits = events
+            .iter()
+            .filter(|e| matches!(e, Event::Damaged(..)))
+            .count();
+        assert_eq!(hits, 1);
+    }
+}
@@ -1,40 +1,56 @@
 use hecs::{CommandBuffer, World};
 use log::debug;
 
-use crate::components::{Action, Animation, AnimationType, Intent, Orientation, Position};
+use crate::components::{
+    Action, Animation, AnimationType, Intent, Orientation, Position, Projectile, StatusEffects,
+    StatusKind,
+};
+use crate::config::Settings;
 
-pub fn animate_system(world: &mut World) {
+pub fn animate_system(world: &mut World, settings: &Settings) {
     let mut cmd = update_animation(world);
     cmd.run_on(world);
-    cmd = add_animation(world);
+    cmd = add_animation(world, settings.animation.frames);
     cmd.run_on(world);
 }
 
-pub fn add_animation(world: &mut World) -> CommandBuffer {
+pub fn add_animation(world: &mut World, frames: u32) -> CommandBuffer {
     let mut cmd = CommandBuffer::new();
 
     world
-        .query::<(&Orientation, &Position, &Intent)>()
+        .query::<(
+            &Orientation,
+            &Position,
+            &Intent,
+            Option<&StatusEffects>,
+            Option<&Projectile>,
+        )>()
         .without::<&Animation>()
         .iter()
-        .for_each(|(e, (orientation, position, intent))| {
+        .for_each(|(e, (orientation, position, intent, status, projectile))| {
             let Intent { action } = intent;
+            let frames = match (status, projectile) {
+                (_, Some(_)) => (frames / 4).max(1),
+                (Some(status), _) if status.has(StatusKind::Haste) => (frames / 2).max(1),
+                _ => frames,
+            };
             match action {
                 Action::Move(direction) => {
                     cmd.insert(
                         e,
-                        (Animation::new(AnimationType::TRANSLATE(
-                            *position, *direction,
-                        )),),
+                        (Animation::new(
+                            AnimationType::TRANSLATE(*position, *direction),
+                            frames,
+                        ),),
                     );
                 }
                 Action::Turn(direction) => {
                     cmd.insert(
                         e,
-                        (Animation::new(AnimationType::ROTATE(
-                            *orientation,
-                            *direction,
-                        )),),
+                        (Animation::new(
+                            AnimationType::ROTATE(*orientation, *direction),
+                            frames,
+                        ),),
                     );
                 }
                 _ => (),
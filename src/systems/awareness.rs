@@ -0,0 +1,62 @@
+use hecs::{CommandBuffer, World};
+
+use crate::{
+    components::{Aware, Monster, Name, Player, Position},
+    events::Event,
+    lighting::{self, Lighting},
+    map::TileMap,
+};
+
+/// How far monsters see in full light, in cells.
+const PERCEPTION_RANGE: f32 = 6.;
+
+/// Monsters notice the party when they perceive it. Once aware, they keep
+/// track of it while it stays in sight and out of the dark.
+pub fn awareness_system<M: Clone>(
+    world: &mut World,
+    events: &mut Vec<Event>,
+    map: &TileMap<M>,
+    lighting: &Lighting,
+) {
+    let Some(party) = world
+        .query::<(&Player, &Position)>()
+        .iter()
+        .next()
+        .map(|(_, (_, position))| map.cell((*position).into()))
+    else {
+        return;
+    };
+
+    let Lighting {
+        blockers,
+        light_map,
+        ..
+    } = lighting;
+
+    let mut cmd = CommandBuffer::new();
+
+    for (e, (_, position, name, aware)) in world
+        .query::<(&Monster, &Position, Option<&Name>, Option<&Aware>)>()
+        .iter()
+    {
+        let cell = map.cell((*position).into());
+
+        let perceived = lighting::perceives(light_map, blockers, cell, PERCEPTION_RANGE, party);
+        let tracked = aware.is_some()
+            && lighting::line_of_sight(blockers, cell, party)
+            && !lighting::is_hidden(light_map, party);
+
+        match (perceived || tracked, aware.is_some()) {
+            (true, false) => {
+                cmd.insert_one(e, Aware);
+                if let Some(name) = name {
+                    events.push(Event::Message(format!("The {} notices you", name.name)));
+                }
+            }
+            (false, true) => cmd.remove_one::<Aware>(e),
+            _ => (),
+        }
+    }
+
+    cmd.run_on(world);
+}
@@ -1,14 +1,25 @@
+use std::f32::consts::{PI, TAU};
+
+use glam::Vec3;
 use gobs::scene::Scene;
 use hecs::{CommandBuffer, World};
 
-use crate::components::{Camera, Intent, Orientation, Position};
+use crate::components::{Animation, AnimationType, Camera, Intent, Orientation, Position};
+use crate::config::Settings;
+use crate::events::Event;
+
+/// Angle under which the view is snapped back to the party facing.
+const RECENTERED: f32 = 0.001;
 
-pub fn camera_system(world: &mut World, scene: &mut Scene) {
-    move_camera(world);
-    update_scene(world, scene);
+pub fn camera_system(world: &mut World, events: &[Event], settings: &Settings, delta: f32) {
+    move_camera(world, settings);
+    recenter(world, settings, delta);
+    effects(world, events, settings, delta);
 }
 
-pub fn move_camera(world: &mut World) {
+pub fn move_camera(world: &mut World, settings: &Settings) {
+    let max_pitch = settings.camera.max_pitch.to_radians();
+
     let mut cmd = CommandBuffer::new();
 
     world
@@ -21,18 +32,15 @@ pub fn move_camera(world: &mut World) {
                 crate::components::Action::Look((dx, dy)) => {
                     if camera.free_view {
                         camera.yaw += dx;
-                        camera.pitch -= dy;
-                    } else {
-                        camera.yaw = 0.;
-                        camera.pitch = 0.;
+                        camera.pitch = (camera.pitch - dy).clamp(-max_pitch, max_pitch);
                     }
                     cmd.remove::<(Intent,)>(e);
                 }
                 crate::components::Action::ControlCamera(lock) => {
                     camera.free_view = *lock;
                     if !lock {
-                        camera.yaw = 0.;
-                        camera.pitch = 0.;
+                        // Return the short way round
+                        camera.yaw = (camera.yaw + PI).rem_euclid(TAU) - PI;
                     }
                     cmd.remove::<(Intent,)>(e);
                 }
@@ -43,13 +51,91 @@ pub fn move_camera(world: &mut World) {
     cmd.run_on(world);
 }
 
-pub fn update_scene(world: &World, scene: &mut Scene) {
+/// Ease the view back to the party facing once free view ends.
+fn recenter(world: &mut World, settings: &Settings, delta: f32) {
+    let ease = 1. - (-settings.camera.recenter_speed * delta).exp();
+
+    for (_, camera) in world.query_mut::<&mut Camera>() {
+        if camera.free_view {
+            continue;
+        }
+
+        camera.yaw -= camera.yaw * ease;
+        camera.pitch -= camera.pitch * ease;
+
+        if camera.yaw.abs() < RECENTERED && camera.pitch.abs() < RECENTERED {
+            camera.yaw = 0.;
+            camera.pitch = 0.;
+        }
+    }
+}
+
+/// Head-bob while stepping and shake when hurt or bumping into walls. The
+/// effects only offset the view, never the position of the party.
+fn effects(world: &mut World, events: &[Event], settings: &Settings, delta: f32) {
+    let effects = &settings.effects;
+
+    for event in events {
+        let (e, shake) = match event {
+            Event::Damaged(e, amount) => (e, effects.damage_shake * *amount as f32),
+            Event::Collided { entity, .. } => (entity, effects.bump_shake),
+            _ => continue,
+        };
+        if let Ok(mut camera) = world.get::<&mut Camera>(*e) {
+            camera.shake(shake);
+        }
+    }
+
+    for (_, (camera, animation)) in world.query_mut::<(&mut Camera, Option<&Animation>)>() {
+        camera.time += delta;
+        camera.shake = (camera.shake - effects.shake_decay * delta).max(0.);
+
+        if !effects.enabled {
+            camera.offset = Vec3::ZERO;
+            camera.yaw_offset = 0.;
+            camera.pitch_offset = 0.;
+            continue;
+        }
+
+        // One bob per step and one nudge per bump, at the middle of the
+        // animation
+        let (bob, nudge) = match animation {
+            Some(animation) => {
+                let arc = (animation.progress as f32 / animation.frames as f32 * PI).sin();
+                match animation.effect {
+                    AnimationType::TRANSLATE(_, direction) if !direction.is_vertical() => {
+                        (arc * effects.head_bob, Vec3::ZERO)
+                    }
+                    AnimationType::BUMP(toward) => (0., toward * arc * effects.bump),
+                    _ => (0., Vec3::ZERO),
+                }
+            }
+            None => (0., Vec3::ZERO),
+        };
+
+        // Smooth pseudo-random noise, stronger as the shake grows
+        let strength = camera.shake * camera.shake;
+        let noise = |frequency: f32, phase: f32| (camera.time * frequency + phase).sin();
+        let angle = effects.shake_angle.to_radians() * strength;
+
+        camera.offset = Vec3::new(
+            noise(37., 0.) * effects.shake_offset * strength,
+            noise(43., 1.) * effects.shake_offset * strength - bob,
+            noise(41., 2.) * effects.shake_offset * strength,
+        ) + nudge;
+        camera.yaw_offset = noise(29., 3.) * angle;
+        camera.pitch_offset = noise(31., 4.) * angle;
+    }
+}
+
+pub fn update_scene(world: &World, scene: &mut Scene, settings: &Settings) {
     world
         .query::<(&Camera, &Position, &Orientation)>()
         .iter()
         .for_each(|(_, (camera, position, orientation))| {
-            scene.camera.position = (*position).into();
-            scene.camera.yaw = orientation.yaw + camera.yaw;
-            scene.camera.pitch = camera.pitch;
+            scene.camera.position =
+                (Into::<Vec3>::into(*position) + camera.offset) * settings.map.tile_size;
+            scene.camera.yaw = orientation.yaw + camera.yaw + camera.yaw_offset;
+            scene.camera.pitch = camera.pitch + camera.pitch_offset;
         });
 }
@@ -1,20 +1,30 @@
-use std::sync::Arc;
-
 use glam::Vec3;
 use hecs::{CommandBuffer, World};
-use log::error;
-
-use gobs::scene::Model;
+use log::debug;
 
 use crate::{
-    components::{Action, Animation, Intent, Orientation, Position},
-    map::TileMap,
+    components::{Action, Animation, AnimationType, Intent, Orientation, Position},
+    config::Settings,
+    events::Event,
+    map::{Cell, TileMap},
     movement,
 };
 
-pub fn collide_system(world: &mut World, map: &TileMap<Arc<Model>>) {
+use super::{hazard, trigger};
+
+/// Stop the moves into walls, closed doors or without a way up or down.
+/// The move is replaced with a short bump against the obstacle.
+pub fn collide_system<M: Clone>(
+    world: &mut World,
+    events: &mut Vec<Event>,
+    map: &TileMap<M>,
+    settings: &Settings,
+) {
     let mut cmd = CommandBuffer::new();
 
+    let doors = trigger::closed_doors(world, map);
+    let frames = (settings.animation.frames / 3).max(1);
+
     world
         .query::<(&Orientation, &Position, &Intent)>()
         .without::<(&Animation,)>()
@@ -30,10 +40,48 @@ pub fn collide_system(world: &mut World, map: &TileMap<Arc<Model>>) {
                 _ => (),
             }
 
-            let new_position = Into::<Vec3>::into(*position) + translation;
-            if map.collides(new_position) {
-                error!("Collide");
-                cmd.remove::<(Intent,)>(e);
+            let position = Into::<Vec3>::into(*position);
+            let new_position = position + translation;
+            let cell = map.cell(new_position);
+            let blocked =
+                |cell: Cell| (map.is_wall(cell) && !map.is_illusion(cell)) || doors.contains(&cell);
+
+            // A diagonal move squeezes between the two cells it passes by
+            let corners = if translation.x != 0. && translation.z != 0. {
+                let from = map.cell(position);
+                [
+                    Cell::new(cell.x, from.y, from.level),
+                    Cell::new(from.x, cell.y, from.level),
+                ]
+                .into_iter()
+                .filter(|c| blocked(*c))
+                .count()
+            } else {
+                0
+            };
+            let squeezed = if settings.movement.cut_corners {
+                corners == 2
+            } else {
+                corners > 0
+            };
+
+            // Going up or down takes a ladder, a rope, or flying through an
+            // opening
+            let grounded = if translation.y != 0. {
+                let from = map.cell(position);
+                let opening = if translation.y > 0. { cell } else { from };
+                let flight = hazard::flying(world, e) && map.is_open(opening);
+                !map.can_climb(from, cell) && !flight
+            } else {
+                false
+            };
+
+            let walled = map.collides(new_position) && !map.is_illusion(cell);
+            if walled || doors.contains(&cell) || squeezed || grounded {
+                debug!("Collide at {}", cell);
+                let bump = Animation::new(AnimationType::BUMP(translation), frames);
+                cmd.insert(e, (bump,));
+                events.push(Event::Collided { entity: e, cell });
             }
         });
 
@@ -0,0 +1,24 @@
+use hecs::{Entity, World};
+
+use crate::{
+    components::{Health, Name, Player},
+    events::Event,
+};
+
+/// Remove the creatures killed during this update.
+pub fn death_system(world: &mut World, events: &mut Vec<Event>) {
+    let dead: Vec<Entity> = world
+        .query::<&Health>()
+        .without::<&Player>()
+        .iter()
+        .filter(|(_, health)| health.is_dead())
+        .map(|(e, _)| e)
+        .collect();
+
+    for entity in dead {
+        if let Ok(name) = world.get::<&Name>(entity) {
+            events.push(Event::Message(format!("The {} dies", name.name)));
+        }
+        let _ = world.despawn(entity);
+    }
+}
@@ -0,0 +1,153 @@
+use glam::Vec3;
+use hecs::{CommandBuffer, Entity, World};
+use log::*;
+
+use crate::{
+    components::{
+        Action, Animation, FreeMovement, Intent, Orientation, Player, Position, StatusEffects,
+        StatusKind,
+    },
+    config::Settings,
+    events::Event,
+    map::{Cell, TileMap},
+    movement::Facing,
+};
+
+use super::trigger;
+
+/// Move entities in free movement continuously, sliding along the walls.
+/// Entering a new cell is reported as a move so that the grid logic keeps
+/// working on the cell the entity is in. Haste doubles the speed, paralysis
+/// stops the entity.
+pub fn free_move_system<M: Clone>(
+    world: &mut World,
+    events: &mut Vec<Event>,
+    map: &TileMap<M>,
+    settings: &Settings,
+    delta: f32,
+) {
+    toggle(world);
+
+    let doors = trigger::closed_doors(world, map);
+    let blocked =
+        |cell: Cell| (map.is_wall(cell) && !map.is_illusion(cell)) || doors.contains(&cell);
+
+    let speed = settings.movement.speed * delta;
+    let turn_speed = settings.movement.turn_speed.to_radians() * delta;
+    let radius = settings.movement.radius;
+
+    let mut moved = Vec::new();
+
+    for (e, (movement, position, orientation, status, player)) in world
+        .query_mut::<(
+            &FreeMovement,
+            &mut Position,
+            &mut Orientation,
+            Option<&StatusEffects>,
+            Option<&Player>,
+        )>()
+        .without::<&Animation>()
+    {
+        let haste = match status {
+            Some(status) if status.has(StatusKind::Paralysis) => continue,
+            Some(status) if status.has(StatusKind::Haste) => 2.,
+            _ => 1.,
+        };
+        let (speed, turn_speed) = (speed * haste, turn_speed * haste);
+
+        orientation.yaw += movement.turn * turn_speed;
+        orientation.facing = Facing::from_yaw(orientation.yaw, orientation.turn == 1);
+
+        let yaw = orientation.yaw;
+        let forward = Vec3::new(yaw.cos(), 0., yaw.sin());
+        let right = Vec3::new(-yaw.sin(), 0., yaw.cos());
+        let velocity =
+            (forward * movement.forward + right * movement.strafe).normalize_or_zero() * speed;
+
+        let from: Vec3 = (*position).into();
+        let mut to = from;
+
+        // Move along each axis separately to slide along walls
+        for step in [Vec3::X * velocity.x, Vec3::Z * velocity.z] {
+            if !overlaps(map, &blocked, to + step, radius) {
+                to += step;
+            }
+        }
+
+        *position = Position::from(to);
+
+        if map.cell(to) != map.cell(from) {
+            moved.push((e, player.is_some()));
+        }
+    }
+
+    for (e, player) in moved {
+        events.push(Event::Moved(e));
+        if player {
+            events.push(Event::Turn);
+        }
+    }
+}
+
+/// Switch the entities asking for it between grid steps and free movement.
+/// Back on the grid, they are put at the center of their cell, facing the
+/// closest direction.
+fn toggle(world: &mut World) {
+    let mut cmd = CommandBuffer::new();
+
+    let toggled: Vec<Entity> = world
+        .query::<&Intent>()
+        .iter()
+        .filter(|(_, intent)| intent.action == Action::ToggleFreeMovement)
+        .map(|(e, _)| e)
+        .collect();
+
+    for e in toggled {
+        cmd.remove::<(Intent,)>(e);
+
+        if world.get::<&FreeMovement>(e).is_ok() {
+            debug!("Grid movement");
+            cmd.remove::<(FreeMovement,)>(e);
+            if let Ok((position, orientation)) =
+                world.query_one_mut::<(&mut Position, &mut Orientation)>(e)
+            {
+                position.translate(Vec3::ZERO, true);
+                orientation.face(orientation.facing);
+            }
+        } else {
+            debug!("Free movement");
+            cmd.insert(e, (FreeMovement::default(),));
+        }
+    }
+
+    cmd.run_on(world);
+}
+
+/// Whether a vertical capsule standing at a position overlaps a blocked
+/// cell. Walls fill whole cells, so only its horizontal section matters.
+fn overlaps<M: Clone>(
+    map: &TileMap<M>,
+    blocked: &impl Fn(Cell) -> bool,
+    position: Vec3,
+    radius: f32,
+) -> bool {
+    let center = map.cell(position);
+
+    (-1..=1).any(|dy| {
+        (-1..=1).any(|dx| {
+            let cell = Cell::new(center.x + dx, center.y + dy, center.level);
+            if !blocked(cell) {
+                return false;
+            }
+
+            let middle = map.position(cell);
+            let closest = Vec3::new(
+                position.x.clamp(middle.x - 0.5, middle.x + 0.5),
+                position.y,
+                position.z.clamp(middle.z - 0.5, middle.z + 0.5),
+            );
+
+            closest.distance(position) < radius
+        })
+    })
+}
@@ -0,0 +1,149 @@
+use hecs::{CommandBuffer, Entity, World};
+use log::*;
+use rand::{rngs::StdRng, seq::SliceRandom};
+
+use crate::{
+    components::{
+        Animation, AntiMagic, Hazard, HazardKind, Health, Orientation, Position, Projectile,
+        StatusEffects, StatusKind,
+    },
+    events::Event,
+    map::{Cell, TileMap},
+};
+
+/// Damage taken for every level fallen.
+const FALL_DAMAGE: u32 = 4;
+
+/// Apply the effect of the cell an entity has just moved on.
+pub fn hazard_system<M: Clone>(
+    world: &mut World,
+    events: &mut Vec<Event>,
+    map: &TileMap<M>,
+    rng: &mut StdRng,
+    moved: &[Entity],
+) {
+    let mut cmd = CommandBuffer::new();
+
+    for &entity in moved {
+        let Ok(position) = world.get::<&Position>(entity).map(|p| *p) else {
+            continue;
+        };
+
+        // Projectiles fly over the floor
+        if world.get::<&Projectile>(entity).is_ok() {
+            continue;
+        }
+
+        let cell = map.cell(position.into());
+        let hazards = hazards_at(world, map, cell);
+
+        if hazards.contains(&HazardKind::AntiMagic) {
+            cmd.insert_one(entity, AntiMagic);
+        } else {
+            cmd.remove_one::<AntiMagic>(entity);
+        }
+
+        for hazard in hazards {
+            match hazard {
+                HazardKind::Spinner => {
+                    if let Ok(mut orientation) = world.get::<&mut Orientation>(entity) {
+                        let facing = *orientation.facings().choose(rng).unwrap();
+                        orientation.face(facing);
+                    }
+                }
+                HazardKind::Teleporter(target) => {
+                    debug!("Teleport to {}", map.cell(target));
+                    move_to(world, entity, Position::from(target));
+                    events.push(Event::Moved(entity));
+                }
+                HazardKind::Pit if levitating(world, entity) => (),
+                HazardKind::Pit => {
+                    if fall(world, events, map, entity, cell) {
+                        events.push(Event::Message("You fall through a pit!".to_string()));
+                    }
+                }
+                HazardKind::AntiMagic => (),
+            }
+        }
+    }
+
+    cmd.run_on(world);
+}
+
+/// Drop what stands over an opening in the floor, unless it climbs or
+/// floats.
+pub fn fall_system<M: Clone>(world: &mut World, events: &mut Vec<Event>, map: &TileMap<M>) {
+    let falling: Vec<(Entity, Cell)> = world
+        .query::<&Position>()
+        .with::<&Health>()
+        .without::<&Animation>()
+        .iter()
+        .map(|(e, position)| (e, map.cell((*position).into())))
+        .filter(|(_, cell)| map.is_open(*cell) && !map.is_climbable(*cell))
+        .collect();
+
+    for (entity, cell) in falling {
+        if !levitating(world, entity) && fall(world, events, map, entity, cell) {
+            events.push(Event::Message("You fall!".to_string()));
+        }
+    }
+}
+
+/// Move an entity down to the first floor or ladder below a cell, hurting
+/// it for every level fallen. Returns false if there is nothing to land on.
+fn fall<M: Clone>(
+    world: &mut World,
+    events: &mut Vec<Event>,
+    map: &TileMap<M>,
+    entity: Entity,
+    cell: Cell,
+) -> bool {
+    let mut target = cell.below();
+    while map.is_open(target) && !map.is_climbable(target) {
+        target = target.below();
+    }
+
+    if !map.is_walkable(target) && !map.is_climbable(target) {
+        return false;
+    }
+
+    let levels = (target.level - cell.level) as u32;
+    debug!("Fall {} levels to {}", levels, target);
+    move_to(world, entity, Position::from(map.position(target)));
+    events.push(Event::Moved(entity));
+
+    if let Ok(mut health) = world.get::<&mut Health>(entity) {
+        health.damage(FALL_DAMAGE * levels);
+        events.push(Event::Damaged(entity, FALL_DAMAGE * levels));
+    }
+
+    true
+}
+
+fn hazards_at<M: Clone>(world: &World, map: &TileMap<M>, cell: Cell) -> Vec<HazardKind> {
+    world
+        .query::<(&Hazard, &Position)>()
+        .iter()
+        .filter(|(_, (_, position))| map.cell((**position).into()) == cell)
+        .map(|(_, (hazard, _))| hazard.kind)
+        .collect()
+}
+
+fn move_to(world: &mut World, entity: Entity, target: Position) {
+    if let Ok(mut position) = world.get::<&mut Position>(entity) {
+        *position = target;
+    }
+}
+
+/// Whether an entity floats over the floor, ignoring pits and openings.
+pub fn levitating(world: &World, entity: Entity) -> bool {
+    world
+        .get::<&StatusEffects>(entity)
+        .is_ok_and(|status| status.has(StatusKind::Levitation) || status.has(StatusKind::Flying))
+}
+
+pub fn flying(world: &World, entity: Entity) -> bool {
+    world
+        .get::<&StatusEffects>(entity)
+        .is_ok_and(|status| status.has(StatusKind::Flying))
+}
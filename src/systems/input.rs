@@ -3,12 +3,19 @@ use hecs::{CommandBuffer, World};
 use gobs::game::input::{Input, Key};
 
 use crate::{
-    components::{Action, Intent, Player},
+    components::{Action, FreeMovement, Intent, Player, ProjectileKind, Sticks, Target},
+    config::Settings,
     events::Event,
+    gamepad::{Axis, Button, GamepadInput},
     movement::Direction,
 };
 
-pub fn input_system(world: &mut World, events: &Vec<Event>, delta: f32) {
+/// Stick travel past which the party steps or turns on the grid.
+const STICK_STEP: f32 = 0.5;
+
+pub fn input_system(world: &mut World, events: &Vec<Event>, delta: f32, settings: &Settings) {
+    let free = steer(world, events);
+
     let mut action = Action::None;
 
     let mut stop = false;
@@ -17,18 +24,62 @@ pub fn input_system(world: &mut World, events: &Vec<Event>, delta: f32) {
         if !stop {
             if let Event::Input(Input::KeyPressed(key)) = e {
                 action = match key {
+                    key if settings.input.is_app_key(key) => Action::None,
+                    Key::A | Key::E | Key::Z | Key::Q | Key::D | Key::S if free => Action::None,
+                    Key::P => Action::ToggleFreeMovement,
                     Key::A => Action::Turn(Direction::Left),
                     Key::E => Action::Turn(Direction::Right),
                     Key::Z => Action::Move(Direction::Forward),
                     Key::Q => Action::Move(Direction::Left),
                     Key::D => Action::Move(Direction::Right),
                     Key::S => Action::Move(Direction::Backward),
-                    _ => Action::None,
+                    Key::Up => Action::Move(Direction::Up),
+                    Key::Down => Action::Move(Direction::Down),
+                    Key::X => Action::Disarm,
+                    Key::Space => Action::Interact,
+                    Key::R => Action::Search,
+                    Key::T => Action::Throw(ProjectileKind::Dagger),
+                    Key::Y => Action::Throw(ProjectileKind::Arrow),
+                    _ => match settings.input.spell_slot(key) {
+                        Some(slot) => Action::Cast(slot, Target::Ahead),
+                        None => Action::None,
+                    },
+                }
+            }
+
+            if let Event::Gamepad(GamepadInput::Pressed(button)) = e {
+                action = match button {
+                    Button::Up
+                    | Button::Down
+                    | Button::Left
+                    | Button::Right
+                    | Button::LeftShoulder
+                    | Button::RightShoulder
+                        if free =>
+                    {
+                        Action::None
+                    }
+                    Button::Up => Action::Move(Direction::Forward),
+                    Button::Down => Action::Move(Direction::Backward),
+                    Button::Left => Action::Turn(Direction::Left),
+                    Button::Right => Action::Turn(Direction::Right),
+                    Button::LeftShoulder => Action::Move(Direction::Left),
+                    Button::RightShoulder => Action::Move(Direction::Right),
+                    Button::South => Action::Interact,
+                    Button::West => Action::Throw(ProjectileKind::Dagger),
+                    Button::East => Action::Cast(0, Target::Ahead),
+                    Button::North => Action::Search,
+                    Button::Select => Action::ToggleFreeMovement,
+                    Button::Start => Action::None,
                 }
             }
 
+            if let Event::Action(chosen) = e {
+                action = *chosen;
+            }
+
             if let Event::Input(Input::MouseMotion(dx, dy)) = e {
-                action = Action::Look((*dx as f32 * delta, *dy as f32 * delta));
+                action = Action::Look(settings.input.look(*dx as f32, *dy as f32, delta));
             }
 
             if let Event::Input(Input::MouseReleased) = e {
@@ -41,6 +92,11 @@ pub fn input_system(world: &mut World, events: &Vec<Event>, delta: f32) {
         }
     });
 
+    let held = sticks(world, events, delta, settings);
+    if action == Action::None {
+        action = held;
+    }
+
     if action != Action::None {
         let mut cmd = CommandBuffer::new();
 
@@ -55,3 +111,140 @@ pub fn input_system(world: &mut World, events: &Vec<Event>, delta: f32) {
         cmd.run_on(world);
     }
 }
+
+/// Hold the movement keys in free movement. Returns whether the player is in
+/// free movement.
+fn steer(world: &mut World, events: &[Event]) -> bool {
+    let Some((_, movement)) = world
+        .query_mut::<&mut FreeMovement>()
+        .with::<&Player>()
+        .into_iter()
+        .next()
+    else {
+        return false;
+    };
+
+    for event in events {
+        match event {
+            Event::Input(Input::KeyPressed(key)) => hold_key(movement, key, 1.),
+            Event::Input(Input::KeyReleased(key)) => hold_key(movement, key, 0.),
+            Event::Gamepad(GamepadInput::Pressed(button)) => hold_button(movement, button, 1.),
+            Event::Gamepad(GamepadInput::Released(button)) => hold_button(movement, button, 0.),
+            _ => (),
+        }
+    }
+
+    true
+}
+
+fn hold_key(movement: &mut FreeMovement, key: &Key, value: f32) {
+    match key {
+        Key::Z => movement.forward = value,
+        Key::S => movement.forward = -value,
+        Key::D => movement.strafe = value,
+        Key::Q => movement.strafe = -value,
+        Key::E => movement.turn = value,
+        Key::A => movement.turn = -value,
+        _ => (),
+    }
+}
+
+fn hold_button(movement: &mut FreeMovement, button: &Button, value: f32) {
+    match button {
+        Button::Up => movement.forward = value,
+        Button::Down => movement.forward = -value,
+        Button::RightShoulder => movement.strafe = value,
+        Button::LeftShoulder => movement.strafe = -value,
+        Button::Right => movement.turn = value,
+        Button::Left => movement.turn = -value,
+        _ => (),
+    }
+}
+
+/// Follow the gamepad sticks: the left one steps and turns, or steers in
+/// free movement, the right one looks around. Held sticks keep acting, so
+/// the action is returned even without new events.
+fn sticks(world: &mut World, events: &[Event], delta: f32, settings: &Settings) -> Action {
+    let axes: Vec<(Axis, f32)> = events
+        .iter()
+        .filter_map(|e| match e {
+            Event::Gamepad(GamepadInput::Axis(axis, value)) => Some((*axis, *value)),
+            _ => None,
+        })
+        .collect();
+
+    let Some((player, _)) = world.query_mut::<&Player>().into_iter().next() else {
+        return Action::None;
+    };
+
+    if world.get::<&Sticks>(player).is_err() {
+        if axes.is_empty() {
+            return Action::None;
+        }
+        let _ = world.insert_one(player, Sticks::default());
+    }
+
+    let Ok((sticks, movement, intent)) =
+        world.query_one_mut::<(&mut Sticks, Option<&mut FreeMovement>, Option<&Intent>)>(player)
+    else {
+        return Action::None;
+    };
+
+    for (axis, value) in &axes {
+        match axis {
+            Axis::LeftX => sticks.left.x = *value,
+            Axis::LeftY => sticks.left.y = *value,
+            Axis::RightX => sticks.right.x = *value,
+            Axis::RightY => sticks.right.y = *value,
+        }
+    }
+
+    let (x, y) = settings.input.stick(sticks.left.x, sticks.left.y);
+    let (look_x, look_y) = settings.input.stick(sticks.right.x, sticks.right.y);
+    let looking = look_x != 0. || look_y != 0.;
+
+    // Steering follows the stick at once, like the held keys
+    let free = movement.is_some();
+    if let Some(movement) = movement {
+        if axes
+            .iter()
+            .any(|(axis, _)| matches!(axis, Axis::LeftX | Axis::LeftY))
+        {
+            movement.forward = y;
+            movement.turn = x;
+        }
+    }
+
+    // The player acts once its current action is done
+    if intent.is_some() {
+        return Action::None;
+    }
+
+    if looking != sticks.looking {
+        sticks.looking = looking;
+        return Action::ControlCamera(looking);
+    }
+
+    if !free && y.abs() >= x.abs() && y.abs() > STICK_STEP {
+        let direction = if y > 0. {
+            Direction::Forward
+        } else {
+            Direction::Backward
+        };
+        return Action::Move(direction);
+    } else if !free && x.abs() > STICK_STEP {
+        let direction = if x > 0. {
+            Direction::Right
+        } else {
+            Direction::Left
+        };
+        return Action::Turn(direction);
+    }
+
+    if looking {
+        let speed = settings.input.stick_speed;
+        return Action::Look(settings.input.look(look_x * speed, -look_y * speed, delta));
+    }
+
+    Action::None
+}
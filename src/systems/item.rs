@@ -0,0 +1,97 @@
+use glam::Vec3;
+use hecs::{CommandBuffer, Entity, World};
+
+use crate::{
+    components::{Action, Door, Intent, Inventory, Item, Orientation, Position},
+    events::Event,
+    map::{Cell, TileMap},
+    movement::{self, Direction},
+};
+
+/// Pick up the items lying on the cell an entity has just moved on, or on
+/// the cell ahead of an entity reaching for them.
+pub fn pickup_system<M: Clone>(world: &mut World, events: &mut Vec<Event>, map: &TileMap<M>) {
+    let mut cmd = CommandBuffer::new();
+
+    let reaches: Vec<(Entity, Cell)> = world
+        .query::<(&Intent, &Position, &Orientation)>()
+        .iter()
+        .filter(|(_, (intent, _, _))| intent.action == Action::PickUp)
+        .map(|(e, (_, position, orientation))| {
+            cmd.remove::<(Intent,)>(e);
+            let front = Into::<Vec3>::into(*position)
+                + movement::get_translation(orientation.facing, Direction::Forward, 1.);
+            (e, map.cell(front))
+        })
+        .collect();
+
+    cmd.run_on(world);
+
+    for (entity, cell) in reaches {
+        let closed = world
+            .query::<(&Door, &Position)>()
+            .iter()
+            .any(|(_, (door, p))| !door.open && map.cell((*p).into()) == cell);
+
+        if closed || !map.is_walkable(cell) || !pick_up(world, events, map, entity, cell) {
+            events.push(Event::Message("There is nothing to pick up".to_string()));
+        }
+    }
+
+    let moved: Vec<Entity> = events
+        .iter()
+        .filter_map(|e| match e {
+            Event::Moved(entity) => Some(*entity),
+            _ => None,
+        })
+        .collect();
+
+    for entity in moved {
+        let Ok(position) = world.get::<&Position>(entity).map(|p| *p) else {
+            continue;
+        };
+
+        pick_up(world, events, map, entity, map.cell(position.into()));
+    }
+}
+
+/// Move the items lying on a cell to the inventory of an entity. Returns
+/// whether anything was picked up.
+fn pick_up<M: Clone>(
+    world: &mut World,
+    events: &mut Vec<Event>,
+    map: &TileMap<M>,
+    entity: Entity,
+    cell: Cell,
+) -> bool {
+    if world.get::<&Inventory>(entity).is_err() {
+        return false;
+    }
+
+    let items: Vec<Entity> = world
+        .query::<(&Item, &Position)>()
+        .iter()
+        .filter(|(_, (_, position))| map.cell((**position).into()) == cell)
+        .map(|(e, _)| e)
+        .collect();
+
+    let mut picked = false;
+
+    for item in items {
+        let Ok(Item { name }) = world.remove_one::<Item>(item) else {
+            continue;
+        };
+        let _ = world.despawn(item);
+
+        let article = if name.starts_with(['a', 'e', 'i', 'o', 'u']) {
+            "an"
+        } else {
+            "a"
+        };
+        events.push(Event::Message(format!("You pick up {} {}", article, name)));
+        world.get::<&mut Inventory>(entity).unwrap().add(name);
+        picked = true;
+    }
+
+    picked
+}
@@ -0,0 +1,112 @@
+use glam::Vec3;
+use gobs::core::entity::light::Light;
+use gobs::scene::Scene;
+use hecs::World;
+
+use crate::components::{
+    Camera, Fuel, LightLevel, LightSource, Position, StatusEffects, StatusKind,
+};
+use crate::config::Settings;
+use crate::events::Event;
+use crate::lighting::{self, Lighting};
+use crate::map::TileMap;
+
+use super::trigger;
+
+pub fn light_system<M: Clone>(
+    world: &mut World,
+    events: &[Event],
+    map: &TileMap<M>,
+    lighting: &mut Lighting,
+    delta: f32,
+) {
+    if lighting.doors_changed || events.iter().any(|e| matches!(e, Event::DoorChanged(_))) {
+        lighting
+            .blockers
+            .set_doors(trigger::closed_doors(world, map));
+        lighting.doors_changed = false;
+    }
+
+    burn_fuel(world, delta);
+    flicker(world, delta);
+    update_light_levels(world, map, lighting);
+}
+
+fn burn_fuel(world: &mut World, delta: f32) {
+    world
+        .query_mut::<(&mut Fuel,)>()
+        .into_iter()
+        .for_each(|(_, (fuel,))| {
+            fuel.remaining = (fuel.remaining - delta).max(0.);
+        });
+}
+
+fn flicker(world: &mut World, delta: f32) {
+    world
+        .query_mut::<(&mut LightSource, Option<&Fuel>)>()
+        .into_iter()
+        .for_each(|(_, (light, fuel))| {
+            light.time += delta;
+            light.intensity = light.flicker.factor(light.time) * fuel.map_or(1., |f| f.strength());
+        });
+}
+
+fn update_light_levels<M: Clone>(world: &mut World, map: &TileMap<M>, lighting: &mut Lighting) {
+    let lights: Vec<(Vec3, LightSource)> = world
+        .query::<(&LightSource, &Position)>()
+        .iter()
+        .map(|(_, (light, position))| ((*position).into(), *light))
+        .collect();
+
+    lighting.light_map = lighting::light_levels(map, &lighting.blockers, &lights);
+    let light_map = &lighting.light_map;
+
+    world
+        .query_mut::<(&mut LightLevel, &Position, Option<&StatusEffects>)>()
+        .into_iter()
+        .for_each(|(_, (light_level, position, status))| {
+            light_level.level = match status {
+                Some(status) if status.has(StatusKind::Blindness) => 0.,
+                _ => light_map.level(map.cell((*position).into())),
+            };
+        });
+}
+
+/// The scene has a single light: use the light source shining the most on
+/// the camera. Carried lights are lifted above the head of the party, and a
+/// blind party sees nothing.
+pub fn update_scene(world: &World, scene: &mut Scene, settings: &Settings) {
+    let mut camera_query = world.query::<(&Camera, &Position, Option<&StatusEffects>)>();
+    let Some((_, (_, camera, status))) = camera_query.iter().next() else {
+        return;
+    };
+    let camera: Vec3 = (*camera).into();
+    let blind = status.is_some_and(|s| s.has(StatusKind::Blindness));
+
+    let brightest = world
+        .query::<(&LightSource, &Position)>()
+        .iter()
+        .map(|(_, (light, position))| {
+            let position: Vec3 = (*position).into();
+            (
+                light.brightness(position.distance(camera)),
+                *light,
+                position,
+            )
+        })
+        .max_by(|a, b| a.0.total_cmp(&b.0));
+
+    let (position, colour) = match brightest {
+        Some((brightness, light, position)) if brightness > 0. && !blind => {
+            (position + Vec3::Y * 0.4, light.colour * light.intensity)
+        }
+        _ => (camera, Vec3::ZERO),
+    };
+
+    let position = position * settings.map.tile_size;
+
+    scene.light = Light::new(
+        (position.x, position.y, position.z),
+        (colour.x, colour.y, colour.z),
+    );
+}
@@ -1,62 +1,114 @@
+use glam::Vec3;
 use hecs::{CommandBuffer, World};
 
 use crate::{
-    components::{Action, Animation, Intent, Orientation, Position},
+    components::{
+        Action, Animation, AnimationType, FreeMovement, Intent, Orientation, Player, Position,
+    },
+    events::Event,
     movement,
 };
 
-pub fn move_system(world: &mut World) {
-    let mut cmd = instant_move(world);
+pub fn move_system(world: &mut World, events: &mut Vec<Event>) {
+    let mut cmd = instant_move(world, events);
     cmd.run_on(world);
 
-    animated_move(world);
+    animated_move(world, events);
 }
 
-fn instant_move(world: &mut World) -> CommandBuffer {
+fn instant_move(world: &mut World, events: &mut Vec<Event>) -> CommandBuffer {
     let mut cmd = CommandBuffer::new();
 
     world
-        .query_mut::<(&mut Orientation, &mut Position, &Intent)>()
+        .query_mut::<(
+            &mut Orientation,
+            &mut Position,
+            &Intent,
+            Option<&FreeMovement>,
+            Option<&Player>,
+        )>()
         .without::<&Animation>()
         .into_iter()
-        .for_each(|(e, (orientation, position, intent))| {
+        .for_each(|(e, (orientation, position, intent, free, player))| {
             let Intent { action } = intent;
             match action {
                 Action::Move(direction) => {
                     let translation = movement::get_translation(orientation.facing, *direction, 1.);
-                    position.translate(translation, true);
+                    arrive(position, translation, free.is_some());
+                    events.push(Event::Moved(e));
                     cmd.remove::<(Intent,)>(e);
                 }
                 Action::Turn(direction) => {
                     orientation.rotate(*direction, 1., true);
                     cmd.remove::<(Intent,)>(e);
                 }
-                _ => (),
+                _ => return,
+            }
+
+            if player.is_some() {
+                events.push(Event::Turn);
             }
         });
 
     cmd
 }
 
-fn animated_move(world: &mut World) {
+fn animated_move(world: &mut World, events: &mut Vec<Event>) {
     world
-        .query_mut::<(&mut Orientation, &mut Position, &Intent, &Animation)>()
+        .query_mut::<(
+            &mut Orientation,
+            &mut Position,
+            &Intent,
+            &Animation,
+            Option<&FreeMovement>,
+            Option<&Player>,
+        )>()
         .into_iter()
-        .for_each(|(_, (orientation, position, intent, animation))| {
-            let Intent { action } = intent;
-            match action {
-                Action::Move(direction) => {
-                    let translation = movement::get_translation(
-                        orientation.facing,
-                        *direction,
-                        1. / animation.frames as f32,
-                    );
-                    position.translate(translation, animation.last());
+        .for_each(
+            |(e, (orientation, position, intent, animation, free, player))| {
+                // Blocked moves only show on the camera
+                if let AnimationType::BUMP(_) = animation.effect {
+                    return;
                 }
-                Action::Turn(direction) => {
-                    orientation.rotate(*direction, 1. / animation.frames as f32, animation.last());
+
+                let Intent { action } = intent;
+                match action {
+                    Action::Move(direction) => {
+                        let translation = movement::get_translation(
+                            orientation.facing,
+                            *direction,
+                            1. / animation.frames as f32,
+                        );
+                        if animation.last() {
+                            arrive(position, translation, free.is_some());
+                            events.push(Event::Moved(e));
+                        } else {
+                            position.translate(translation, false);
+                        }
+                    }
+                    Action::Turn(direction) => {
+                        orientation.rotate(
+                            *direction,
+                            1. / animation.frames as f32,
+                            animation.last(),
+                        );
+                    }
+                    _ => panic!(),
                 }
-                _ => panic!(),
-            }
-        });
+
+                if animation.last() && player.is_some() {
+                    events.push(Event::Turn);
+                }
+            },
+        );
+}
+
+/// Last step of a move, ending on the cell center. In free movement only the
+/// height is snapped, to the level, and the entity keeps its place in the
+/// cell.
+fn arrive(position: &mut Position, translation: Vec3, free: bool) {
+    position.translate(translation, !free);
+    if free {
+        position.y = position.y.round();
+    }
 }
@@ -0,0 +1,168 @@
+use hecs::{CommandBuffer, Entity, World};
+use log::*;
+
+use crate::{
+    components::{
+        Action, Animation, Health, Intent, Inventory, Item, Orientation, Position, Projectile,
+        ProjectileKind, Trigger, TriggerKind,
+    },
+    events::Event,
+    map::{Cell, TileMap},
+    movement::Direction,
+    spawner, spells,
+};
+
+use super::trigger;
+
+/// Throw projectiles and move them one cell at a time until they hit
+/// something or run out of range.
+pub fn projectile_system<M: Clone>(world: &mut World, events: &mut Vec<Event>, map: &TileMap<M>) {
+    throw(world, events);
+
+    let flying: Vec<(Entity, Projectile, Cell, Orientation)> = world
+        .query::<(&Projectile, &Position, &Orientation)>()
+        .without::<(&Intent, &Animation)>()
+        .iter()
+        .map(|(e, (projectile, position, orientation))| {
+            (e, *projectile, map.cell((*position).into()), *orientation)
+        })
+        .collect();
+
+    for (entity, projectile, cell, orientation) in flying {
+        if projectile.range == 0 {
+            land(world, events, map, entity, projectile, cell);
+            continue;
+        }
+
+        let next = spells::step(cell, orientation.facing, Direction::Forward, 1);
+
+        let blocked = (map.is_wall(next) && !map.is_illusion(next))
+            || trigger::closed_doors(world, map).contains(&next);
+
+        if blocked {
+            hit_switch(world, events, map, projectile, next);
+            land(world, events, map, entity, projectile, cell);
+            continue;
+        }
+
+        let target = world
+            .query::<(&Health, &Position)>()
+            .iter()
+            .find(|(e, (_, position))| {
+                *e != projectile.owner && map.cell((**position).into()) == next
+            })
+            .map(|(e, _)| e);
+
+        if let Some(target) = target {
+            let damage = projectile.kind.damage();
+            world.get::<&mut Health>(target).unwrap().damage(damage);
+            events.push(Event::Damaged(target, damage));
+            land(world, events, map, entity, projectile, next);
+            continue;
+        }
+
+        if let Ok(mut projectile) = world.get::<&mut Projectile>(entity) {
+            projectile.range -= 1;
+        }
+        let _ = world.insert_one(
+            entity,
+            Intent {
+                action: Action::Move(Direction::Forward),
+            },
+        );
+    }
+}
+
+fn throw(world: &mut World, events: &mut Vec<Event>) {
+    let mut cmd = CommandBuffer::new();
+
+    let throws: Vec<(Entity, ProjectileKind)> = world
+        .query::<&Intent>()
+        .iter()
+        .filter_map(|(e, intent)| match intent.action {
+            Action::Throw(kind) => {
+                cmd.remove::<(Intent,)>(e);
+                Some((e, kind))
+            }
+            _ => None,
+        })
+        .collect();
+
+    cmd.run_on(world);
+
+    for (thrower, kind) in throws {
+        let Some(item) = kind.item() else {
+            continue;
+        };
+
+        let thrown = world
+            .get::<&mut Inventory>(thrower)
+            .map(|mut inventory| {
+                (kind != ProjectileKind::Arrow || inventory.has("bow")) && inventory.remove(item)
+            })
+            .unwrap_or(false);
+
+        if !thrown {
+            events.push(Event::Message(format!("You have no {} to throw", item)));
+            continue;
+        }
+
+        let Ok((position, facing)) = world
+            .query_one_mut::<(&Position, &Orientation)>(thrower)
+            .map(|(p, o)| (*p, o.facing))
+        else {
+            continue;
+        };
+
+        spawner::spawn_projectile(world, position.into(), facing, kind, thrower);
+    }
+}
+
+/// Levers and buttons can be operated from afar.
+fn hit_switch<M: Clone>(
+    world: &World,
+    events: &mut Vec<Event>,
+    map: &TileMap<M>,
+    projectile: Projectile,
+    cell: Cell,
+) {
+    let switch = world
+        .query::<(&Trigger, &Position)>()
+        .iter()
+        .find(|(_, (trigger, position))| {
+            trigger.kind != TriggerKind::Plate && map.cell((**position).into()) == cell
+        })
+        .map(|(e, _)| e);
+
+    if let Some(trigger) = switch {
+        events.push(Event::Triggered {
+            trigger,
+            by: projectile.owner,
+        });
+    }
+}
+
+/// Drop the projectile on a cell, where it can be picked up. It belongs to
+/// the party rather than the map, so it stays when the map is reloaded.
+fn land<M: Clone>(
+    world: &mut World,
+    events: &mut Vec<Event>,
+    map: &TileMap<M>,
+    entity: Entity,
+    projectile: Projectile,
+    cell: Cell,
+) {
+    debug!("{:?} lands at {}", projectile.kind, cell);
+
+    let _ = world.despawn(entity);
+
+    match projectile.kind.item() {
+        Some(name) => {
+            let item = Item {
+                name: name.to_string(),
+            };
+            world.spawn((Position::from(map.position(cell)), item));
+        }
+        None => events.push(Event::Message("The fireball bursts".to_string())),
+    }
+}
@@ -0,0 +1,189 @@
+use hecs::{Entity, World};
+use log::*;
+
+use crate::{
+    components::{Door, DoorOperation, Health, Inventory, Orientation, Player, Position},
+    events::Event,
+    map::{Cell, TileMap},
+    scripting::{self, Command, Context, Scripts},
+    spawner,
+};
+
+use super::trigger;
+
+enum Hook {
+    LevelLoad(i32),
+    EnterCell(Cell),
+    Interact(Cell, bool),
+    Turn,
+}
+
+/// Call the script hooks for what happened to the player during this
+/// update.
+pub fn script_system<M: Clone>(
+    world: &mut World,
+    events: &mut Vec<Event>,
+    map: &TileMap<M>,
+    scripts: &mut Scripts,
+) {
+    let Some(player) = world.query::<&Player>().iter().next().map(|(e, _)| e) else {
+        return;
+    };
+
+    let mut hooks = Vec::new();
+
+    if let Some(cell) = position(world, map, player) {
+        if scripts.level != Some(cell.level) {
+            scripts.level = Some(cell.level);
+            hooks.push(Hook::LevelLoad(cell.level));
+        }
+    }
+
+    for event in events.iter() {
+        match event {
+            Event::Moved(e) if *e == player => {
+                if let Some(cell) = position(world, map, player) {
+                    hooks.push(Hook::EnterCell(cell));
+                }
+            }
+            Event::Interacted { by, cell, handled } if *by == player => {
+                hooks.push(Hook::Interact(*cell, *handled))
+            }
+            Event::Turn => hooks.push(Hook::Turn),
+            _ => (),
+        }
+    }
+
+    for hook in hooks {
+        let context = context(world, map, player);
+
+        let commands = match hook {
+            Hook::LevelLoad(level) => {
+                scripts
+                    .call(scripting::ON_LEVEL_LOAD, context, (level as i64,))
+                    .1
+            }
+            Hook::EnterCell(cell) => {
+                scripts
+                    .call(scripting::ON_ENTER_CELL, context, coordinates(cell))
+                    .1
+            }
+            Hook::Interact(cell, handled) => {
+                let (result, commands) =
+                    scripts.call(scripting::ON_INTERACT, context, coordinates(cell));
+                let scripted = result.and_then(|r| r.as_bool().ok()).unwrap_or(false);
+                if !handled && !scripted {
+                    events.push(Event::Message("There is nothing here".to_string()));
+                }
+                commands
+            }
+            Hook::Turn => scripts.call(scripting::ON_TURN, context, ()).1,
+        };
+
+        apply(world, events, map, player, commands);
+    }
+}
+
+fn coordinates(cell: Cell) -> (i64, i64, i64) {
+    (cell.x as i64, cell.y as i64, cell.level as i64)
+}
+
+fn position<M: Clone>(world: &World, map: &TileMap<M>, entity: Entity) -> Option<Cell> {
+    world
+        .get::<&Position>(entity)
+        .ok()
+        .map(|p| map.cell((*p).into()))
+}
+
+fn context<M: Clone>(world: &World, map: &TileMap<M>, player: Entity) -> Context {
+    let facing = world
+        .get::<&Orientation>(player)
+        .map(|o| format!("{:?}", o.facing))
+        .unwrap_or_default();
+
+    let health = world
+        .get::<&Health>(player)
+        .map(|h| (h.current, h.max))
+        .unwrap_or((0, 0));
+
+    let items = world
+        .get::<&Inventory>(player)
+        .map(|i| i.items.clone())
+        .unwrap_or_default();
+
+    let doors = world
+        .query::<(&Door, &Position)>()
+        .iter()
+        .map(|(_, (door, p))| (map.cell((*p).into()), door.open))
+        .collect();
+
+    Context {
+        cell: position(world, map, player),
+        facing,
+        health,
+        items,
+        doors,
+        commands: Vec::new(),
+    }
+}
+
+fn apply<M: Clone>(
+    world: &mut World,
+    events: &mut Vec<Event>,
+    map: &TileMap<M>,
+    player: Entity,
+    commands: Vec<Command>,
+) {
+    for command in commands {
+        debug!("Script command {:?}", command);
+
+        match command {
+            Command::Teleport(cell) => {
+                if !map.is_walkable(cell) {
+                    warn!("Script cannot teleport to {}", cell);
+                    continue;
+                }
+                if let Ok(mut position) = world.get::<&mut Position>(player) {
+                    *position = Position::from(map.position(cell));
+                    events.push(Event::Moved(player));
+                }
+            }
+            Command::Damage(amount) => {
+                if let Ok(mut health) = world.get::<&mut Health>(player) {
+                    health.damage(amount);
+                    events.push(Event::Damaged(player, amount));
+                }
+            }
+            Command::Heal(amount) => {
+                if let Ok(mut health) = world.get::<&mut Health>(player) {
+                    health.heal(amount);
+                }
+            }
+            Command::Door(cell, open) => match trigger::door_at(world, map, cell) {
+                Some(door) => {
+                    let operation = if open {
+                        DoorOperation::Open
+                    } else {
+                        DoorOperation::Close
+                    };
+                    trigger::operate_door(world, events, map, door, operation);
+                }
+                None => warn!("Script: no door at {}", cell),
+            },
+            Command::GiveItem(item) => {
+                if let Ok(mut inventory) = world.get::<&mut Inventory>(player) {
+                    inventory.add(item);
+                }
+            }
+            Command::TakeItem(item) => {
+                if let Ok(mut inventory) = world.get::<&mut Inventory>(player) {
+                    inventory.remove(&item);
+                }
+            }
+            Command::Spawn(cell, name, health) => {
+                spawner::spawn_monster(world, map.position(cell), &name, health);
+            }
+            Command::Message(message) => events.push(Event::Message(message)),
+        }
+    }
+}
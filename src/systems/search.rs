@@ -0,0 +1,82 @@
+use hecs::{CommandBuffer, Entity, World};
+use rand::rngs::StdRng;
+
+use crate::{
+    components::{Action, Intent, LightLevel, Position, Secret, Skills},
+    events::Event,
+    lighting::HIDE_THRESHOLD,
+    map::{Cell, TileMap},
+};
+
+/// Difficulty of noticing an illusory wall.
+const ILLUSION_DIFFICULTY: u32 = 10;
+
+/// Search the cells around an entity for secret doors and illusory walls.
+pub fn search_system<M: Clone>(
+    world: &mut World,
+    events: &mut Vec<Event>,
+    map: &TileMap<M>,
+    rng: &mut StdRng,
+) {
+    let mut cmd = CommandBuffer::new();
+
+    let searches: Vec<(Skills, Cell, f32)> = world
+        .query::<(&Intent, &Position, Option<&Skills>, Option<&LightLevel>)>()
+        .iter()
+        .filter(|(_, (intent, _, _, _))| intent.action == Action::Search)
+        .map(|(e, (_, position, skills, light))| {
+            cmd.remove::<(Intent,)>(e);
+            let skills = skills.copied().unwrap_or(Skills {
+                perception: 0,
+                disarm: 0,
+            });
+            let light = light.map(|l| l.level).unwrap_or(1.);
+            (skills, map.cell((*position).into()), light)
+        })
+        .collect();
+
+    cmd.run_on(world);
+
+    for (skills, cell, light) in searches {
+        if light < HIDE_THRESHOLD {
+            events.push(Event::Message("It is too dark to search".to_string()));
+            continue;
+        }
+
+        let neighbours = cell.neighbours();
+
+        let secrets: Vec<(Entity, Secret)> = world
+            .query::<(&Secret, &Position)>()
+            .iter()
+            .filter(|(_, (_, position))| neighbours.contains(&map.cell((**position).into())))
+            .map(|(e, (secret, _))| (e, *secret))
+            .collect();
+
+        let mut found = false;
+
+        for (door, secret) in secrets {
+            if Skills::check(rng, skills.perception, secret.difficulty) {
+                let _ = world.remove_one::<Secret>(door);
+                events.push(Event::DoorChanged(door));
+                events.push(Event::Message("You find a secret door".to_string()));
+                found = true;
+            }
+        }
+
+        for (neighbour, direction) in neighbours.iter().zip(["north", "east", "south", "west"]) {
+            if map.is_illusion(*neighbour)
+                && Skills::check(rng, skills.perception, ILLUSION_DIFFICULTY)
+            {
+                events.push(Event::Message(format!(
+                    "The wall to the {} is an illusion",
+                    direction
+                )));
+                found = true;
+            }
+        }
+
+        if !found {
+            events.push(Event::Message("You find nothing".to_string()));
+        }
+    }
+}
@@ -0,0 +1,291 @@
+use std::collections::HashSet;
+
+use hecs::{CommandBuffer, Entity, World};
+use log::*;
+
+use crate::{
+    components::{
+        Action, AntiMagic, Conjured, Door, DoorOperation, Fuel, Health, Intent, LightSource, Mana,
+        Orientation, Position, Spellbook, StatusEffects, Target,
+    },
+    config::Settings,
+    events::Event,
+    map::{Cell, TileMap},
+    movement::{Direction, Facing},
+    spawner,
+    spells::{self, Area, Effect, Spell},
+};
+
+use super::trigger;
+
+pub fn spell_system<M: Clone>(
+    world: &mut World,
+    events: &mut Vec<Event>,
+    map: &TileMap<M>,
+    settings: &Settings,
+    delta: f32,
+) {
+    recover(world, settings, delta);
+    cast(world, events, map, settings);
+    dispel(world);
+}
+
+/// Regenerate mana and count down the spell cooldowns.
+fn recover(world: &mut World, settings: &Settings, delta: f32) {
+    for (_, mana) in world.query_mut::<&mut Mana>() {
+        mana.recover(settings.magic.mana_regen * delta);
+    }
+
+    for (_, spellbook) in world.query_mut::<&mut Spellbook>() {
+        for cooldown in &mut spellbook.cooldowns {
+            *cooldown = (*cooldown - delta).max(0.);
+        }
+    }
+}
+
+fn cast<M: Clone>(
+    world: &mut World,
+    events: &mut Vec<Event>,
+    map: &TileMap<M>,
+    settings: &Settings,
+) {
+    let mut cmd = CommandBuffer::new();
+
+    let casts: Vec<(Entity, usize, Target)> = world
+        .query::<&Intent>()
+        .iter()
+        .filter_map(|(e, intent)| match intent.action {
+            Action::Cast(slot, target) => {
+                cmd.remove::<(Intent,)>(e);
+                Some((e, slot, target))
+            }
+            _ => None,
+        })
+        .collect();
+
+    cmd.run_on(world);
+
+    for (caster, slot, target) in casts {
+        if let Err(message) = cast_spell(world, events, map, settings, caster, slot, target) {
+            events.push(Event::Message(message));
+        }
+    }
+}
+
+fn cast_spell<M: Clone>(
+    world: &mut World,
+    events: &mut Vec<Event>,
+    map: &TileMap<M>,
+    settings: &Settings,
+    caster: Entity,
+    slot: usize,
+    target: Target,
+) -> Result<(), String> {
+    let name = world
+        .get::<&Spellbook>(caster)
+        .ok()
+        .and_then(|spellbook| spellbook.spells.get(slot).cloned())
+        .ok_or("You don't know that spell")?;
+
+    let spell = settings
+        .magic
+        .spells
+        .iter()
+        .find(|spell| spell.name == name)
+        .ok_or_else(|| format!("Unknown spell {}", name))?;
+
+    if world.get::<&AntiMagic>(caster).is_ok() {
+        return Err("Your magic fizzles".to_string());
+    }
+
+    if world.get::<&Spellbook>(caster).unwrap().cooldowns[slot] > 0. {
+        return Err(format!("{} is not ready", name));
+    }
+
+    let (cell, facing) = world
+        .query_one_mut::<(&Position, &Orientation)>(caster)
+        .map(|(position, orientation)| (map.cell((*position).into()), orientation.facing))
+        .map_err(|_| "You cannot cast here")?;
+
+    let (target, distance) = aim(world, map, caster, cell, facing, spell, target)?;
+
+    let enough = world
+        .get::<&mut Mana>(caster)
+        .map(|mut mana| mana.spend(spell.cost))
+        .unwrap_or(false);
+    if !enough {
+        return Err("Not enough mana".to_string());
+    }
+
+    world.get::<&mut Spellbook>(caster).unwrap().cooldowns[slot] = spell.cooldown;
+
+    debug!("Cast {} at {}", spell.name, target);
+    events.push(Event::Message(format!("You cast {}", spell.name)));
+
+    let cells: Vec<Cell> = spells::cells(spell.area, cell, facing, target, distance)
+        .into_iter()
+        .filter(|c| !map.is_wall(*c) || map.is_illusion(*c))
+        .collect();
+
+    for effect in &spell.effects {
+        apply(world, events, map, caster, *effect, &cells, target);
+    }
+
+    Ok(())
+}
+
+/// Target cell of a spell and its distance to the caster. Spells aimed
+/// ahead stop at the first wall, closed door or creature.
+fn aim<M: Clone>(
+    world: &World,
+    map: &TileMap<M>,
+    caster: Entity,
+    cell: Cell,
+    facing: Facing,
+    spell: &Spell,
+    target: Target,
+) -> Result<(Cell, i32), String> {
+    if spell.area == Area::Caster {
+        return Ok((cell, 0));
+    }
+
+    let range = spell.range as i32;
+
+    match target {
+        Target::Cell(target) => {
+            let distance = (target.x - cell.x).abs() + (target.y - cell.y).abs();
+            if target.level != cell.level || distance > range {
+                return Err("The target is out of range".to_string());
+            }
+            Ok((target, distance))
+        }
+        Target::Ahead => {
+            let creatures: HashSet<Cell> = world
+                .query::<(&Health, &Position)>()
+                .iter()
+                .filter(|(e, _)| *e != caster)
+                .map(|(_, (_, position))| map.cell((*position).into()))
+                .collect();
+            let doors = trigger::closed_doors(world, map);
+
+            let mut aimed = (cell, 0);
+            for distance in 1..=range {
+                let next = spells::step(cell, facing, Direction::Forward, distance);
+                if map.is_wall(next) && !map.is_illusion(next) {
+                    break;
+                }
+                aimed = (next, distance);
+                if creatures.contains(&next) || doors.contains(&next) {
+                    break;
+                }
+            }
+
+            if aimed.1 == 0 {
+                return Err("There is no room to cast".to_string());
+            }
+
+            Ok(aimed)
+        }
+    }
+}
+
+fn apply<M: Clone>(
+    world: &mut World,
+    events: &mut Vec<Event>,
+    map: &TileMap<M>,
+    caster: Entity,
+    effect: Effect,
+    cells: &[Cell],
+    target: Cell,
+) {
+    let in_area = |world: &World| -> Vec<Entity> {
+        world
+            .query::<(&Health, &Position)>()
+            .iter()
+            .filter(|(_, (_, position))| cells.contains(&map.cell((**position).into())))
+            .map(|(e, _)| e)
+            .collect()
+    };
+
+    match effect {
+        Effect::Damage(amount) => {
+            for entity in in_area(world) {
+                world.get::<&mut Health>(entity).unwrap().damage(amount);
+                events.push(Event::Damaged(entity, amount));
+            }
+        }
+        Effect::Heal(amount) => {
+            for entity in in_area(world) {
+                world.get::<&mut Health>(entity).unwrap().heal(amount);
+            }
+        }
+        Effect::Light(duration) => {
+            world.spawn((
+                Position::from(map.position(target)),
+                LightSource::crystal(),
+                Fuel::new(duration),
+                Conjured,
+            ));
+        }
+        Effect::OpenDoor => {
+            for cell in cells {
+                let Some(door) = trigger::door_at(world, map, *cell) else {
+                    continue;
+                };
+                // Locked doors need their key or trigger
+                if world.get::<&Door>(door).map(|d| d.locked).unwrap_or(false) {
+                    events.push(Event::Message("The door is locked".to_string()));
+                } else {
+                    trigger::operate_door(world, events, map, door, DoorOperation::Open);
+                }
+            }
+        }
+        Effect::Status(effect) => {
+            for entity in in_area(world) {
+                let added = world
+                    .get::<&mut StatusEffects>(entity)
+                    .map(|mut status| status.add(effect))
+                    .is_ok();
+                if !added {
+                    let mut status = StatusEffects::default();
+                    status.add(effect);
+                    let _ = world.insert_one(entity, status);
+                }
+            }
+        }
+        Effect::Projectile(kind) => {
+            let Ok((position, facing)) = world
+                .query_one_mut::<(&Position, &Orientation)>(caster)
+                .map(|(p, o)| (*p, o.facing))
+            else {
+                return;
+            };
+            spawner::spawn_projectile(world, position.into(), facing, kind, caster);
+        }
+        Effect::Teleport => {
+            let blocked = !map.is_walkable(target)
+                || trigger::closed_doors(world, map).contains(&target)
+                || !in_area(world).is_empty();
+            if blocked {
+                events.push(Event::Message("The spell fails to move you".to_string()));
+            } else if let Ok(mut position) = world.get::<&mut Position>(caster) {
+                *position = Position::from(map.position(target));
+                events.push(Event::Moved(caster));
+            }
+        }
+    }
+}
+
+/// Remove the conjured lights that burnt out.
+fn dispel(world: &mut World) {
+    let spent: Vec<Entity> = world
+        .query::<(&Conjured, &Fuel)>()
+        .iter()
+        .filter(|(_, (_, fuel))| fuel.remaining <= 0.)
+        .map(|(e, _)| e)
+        .collect();
+
+    for entity in spent {
+        let _ = world.despawn(entity);
+    }
+}
@@ -0,0 +1,75 @@
+use hecs::{CommandBuffer, Entity, World};
+
+use crate::{
+    components::{Animation, Duration, Health, Intent, Player, StatusEffects, StatusKind},
+    events::Event,
+};
+
+/// Cancel the actions of paralysed entities before they start. Looking
+/// around is still possible.
+pub fn paralysis_system(world: &mut World, events: &mut Vec<Event>) {
+    let mut cmd = CommandBuffer::new();
+
+    world
+        .query::<(&Intent, &StatusEffects, Option<&Player>)>()
+        .without::<&Animation>()
+        .iter()
+        .filter(|(_, (intent, status, _))| {
+            intent.action.is_deed() && status.has(StatusKind::Paralysis)
+        })
+        .for_each(|(e, (_, _, player))| {
+            cmd.remove::<(Intent,)>(e);
+            if player.is_some() {
+                events.push(Event::Message("You cannot move!".to_string()));
+            }
+        });
+
+    cmd.run_on(world);
+}
+
+/// Apply poison and regeneration every second, and remove expired effects.
+pub fn status_system(world: &mut World, events: &mut Vec<Event>, delta: f32) {
+    let turns = events.iter().filter(|e| matches!(e, Event::Turn)).count() as u32;
+
+    let mut damaged: Vec<(Entity, u32)> = Vec::new();
+
+    world
+        .query_mut::<(&mut StatusEffects, Option<&mut Health>, Option<&Player>)>()
+        .into_iter()
+        .for_each(|(e, (status, mut health, player))| {
+            for effect in &mut status.effects {
+                effect.timer += delta;
+                while effect.timer >= 1. {
+                    effect.timer -= 1.;
+                    match (effect.kind, health.as_deref_mut()) {
+                        (StatusKind::Poison, Some(health)) => {
+                            health.damage(effect.strength);
+                            damaged.push((e, effect.strength));
+                        }
+                        (StatusKind::Regeneration, Some(health)) => health.heal(effect.strength),
+                        _ => (),
+                    }
+                }
+
+                effect.duration = match effect.duration {
+                    Duration::Seconds(seconds) => Duration::Seconds(seconds - delta),
+                    Duration::Turns(n) => Duration::Turns(n.saturating_sub(turns)),
+                };
+            }
+
+            for effect in status.effects.iter().filter(|e| e.duration.expired()) {
+                if player.is_some() {
+                    events.push(Event::Message(format!(
+                        "You are no longer {}",
+                        effect.kind.name()
+                    )));
+                }
+            }
+
+            status.effects.retain(|e| !e.duration.expired());
+        });
+
+    for (entity, amount) in damaged {
+        events.push(Event::Damaged(entity, amount));
+    }
+}
@@ -0,0 +1,183 @@
+use glam::Vec3;
+use hecs::{CommandBuffer, Entity, World};
+use log::*;
+use rand::rngs::StdRng;
+
+use crate::{
+    components::{
+        Action, Duration, Health, Intent, Orientation, Position, Projectile, Skills, StatusEffect,
+        StatusEffects, StatusKind, Trap, TrapKind,
+    },
+    events::Event,
+    map::{Cell, TileMap},
+    movement::{self, Direction},
+};
+
+/// Trigger and detect the traps where entities have just moved.
+pub fn trap_system<M: Clone>(
+    world: &mut World,
+    events: &mut Vec<Event>,
+    map: &TileMap<M>,
+    rng: &mut StdRng,
+    moved: &[Entity],
+) {
+    for &entity in moved {
+        trigger(world, events, map, entity);
+        detect(world, events, map, rng, entity);
+    }
+}
+
+fn trap_at<M: Clone>(world: &World, map: &TileMap<M>, cell: Cell) -> Option<Entity> {
+    world
+        .query::<(&Trap, &Position)>()
+        .iter()
+        .find(|(_, (_, position))| map.cell((**position).into()) == cell)
+        .map(|(e, _)| e)
+}
+
+fn trigger<M: Clone>(world: &mut World, events: &mut Vec<Event>, map: &TileMap<M>, entity: Entity) {
+    let Ok(position) = world.get::<&Position>(entity).map(|p| *p) else {
+        return;
+    };
+
+    if world.get::<&Projectile>(entity).is_ok() {
+        return;
+    }
+
+    let Some(trap_entity) = trap_at(world, map, map.cell(position.into())) else {
+        return;
+    };
+
+    if world.get::<&Trap>(trap_entity).unwrap().kind == TrapKind::Pit
+        && super::hazard::levitating(world, entity)
+    {
+        return;
+    }
+
+    let trap = {
+        let mut trap = world.get::<&mut Trap>(trap_entity).unwrap();
+        if !trap.armed {
+            return;
+        }
+        trap.hidden = false;
+        *trap
+    };
+
+    debug!("Trap {:?} triggered", trap.kind);
+    events.push(Event::Message(format!("A {} trap!", trap_name(&trap))));
+
+    if let TrapKind::Teleport(target) = trap.kind {
+        if let Ok(mut position) = world.get::<&mut Position>(entity) {
+            *position = Position::from(target);
+            events.push(Event::Moved(entity));
+        }
+    }
+
+    if trap.kind == TrapKind::Darts {
+        if let Ok(mut status) = world.get::<&mut StatusEffects>(entity) {
+            status.add(StatusEffect::new(
+                StatusKind::Poison,
+                Duration::Seconds(5.),
+                1,
+            ));
+        }
+    }
+
+    let damage = trap.damage();
+    if damage > 0 {
+        if let Ok(mut health) = world.get::<&mut Health>(entity) {
+            health.damage(damage);
+            events.push(Event::Damaged(entity, damage));
+        }
+    }
+}
+
+/// Perception check for hidden traps around an entity after it moved.
+fn detect<M: Clone>(
+    world: &mut World,
+    events: &mut Vec<Event>,
+    map: &TileMap<M>,
+    rng: &mut StdRng,
+    entity: Entity,
+) {
+    let Ok((skills, position)) = world
+        .query_one_mut::<(&Skills, &Position)>(entity)
+        .map(|(s, p)| (*s, *p))
+    else {
+        return;
+    };
+
+    let cell = map.cell(position.into());
+
+    world
+        .query_mut::<(&mut Trap, &Position)>()
+        .into_iter()
+        .filter(|(_, (trap, _))| trap.hidden)
+        .for_each(|(_, (trap, trap_position))| {
+            let trap_cell = map.cell((*trap_position).into());
+            let near = cell.neighbours().contains(&trap_cell);
+
+            if near && Skills::check(rng, skills.perception, trap.difficulty) {
+                trap.hidden = false;
+                events.push(Event::Message(format!(
+                    "You notice a {} trap",
+                    trap_name(trap)
+                )));
+            }
+        });
+}
+
+/// Disarm the trap in front of the entity.
+pub fn disarm_system<M: Clone>(
+    world: &mut World,
+    events: &mut Vec<Event>,
+    map: &TileMap<M>,
+    rng: &mut StdRng,
+) {
+    let mut cmd = CommandBuffer::new();
+
+    let attempts: Vec<(Entity, Skills, Cell)> = world
+        .query::<(&Intent, &Orientation, &Position, Option<&Skills>)>()
+        .iter()
+        .filter(|(_, (intent, _, _, _))| intent.action == Action::Disarm)
+        .map(|(e, (_, orientation, position, skills))| {
+            cmd.remove::<(Intent,)>(e);
+            let front = Into::<Vec3>::into(*position)
+                + movement::get_translation(orientation.facing, Direction::Forward, 1.);
+            let skills = skills.copied().unwrap_or(Skills {
+                perception: 0,
+                disarm: 0,
+            });
+            (e, skills, map.cell(front))
+        })
+        .collect();
+
+    cmd.run_on(world);
+
+    for (_, skills, cell) in attempts {
+        let trap = trap_at(world, map, cell).and_then(|e| world.get::<&mut Trap>(e).ok());
+
+        let message = match trap {
+            Some(mut trap) if trap.armed && !trap.hidden => {
+                if Skills::check(rng, skills.disarm, trap.difficulty) {
+                    trap.armed = false;
+                    format!("You disarm the {} trap", trap_name(&trap))
+                } else {
+                    format!("You fail to disarm the {} trap", trap_name(&trap))
+                }
+            }
+            _ => "There is nothing to disarm".to_string(),
+        };
+
+        events.push(Event::Message(message));
+    }
+}
+
+fn trap_name(trap: &Trap) -> &'static str {
+    match trap.kind {
+        TrapKind::Pit => "pit",
+        TrapKind::Spikes => "spike",
+        TrapKind::Darts => "dart",
+        TrapKind::Teleport(_) => "teleport",
+    }
+}
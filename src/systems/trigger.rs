@@ -0,0 +1,246 @@
+use std::collections::HashMap;
+
+use glam::Vec3;
+use hecs::{CommandBuffer, Entity, World};
+use log::*;
+
+use crate::{
+    components::{
+        Action, Door, DoorOperation, Intent, Inventory, Orientation, Position, Projectile, Secret,
+        Trigger, TriggerAction, TriggerKind,
+    },
+    events::Event,
+    map::{Cell, TileMap},
+    movement::{self, Direction},
+    spawner,
+};
+
+/// Activate levers, buttons and doors in front of interacting entities and
+/// pressure plates under moving ones, then run the actions of the triggers
+/// whose conditions are met.
+pub fn trigger_system<M: Clone>(world: &mut World, events: &mut Vec<Event>, map: &TileMap<M>) {
+    interact(world, events, map);
+
+    if events.iter().any(|e| matches!(e, Event::Moved(_))) {
+        press_plates(world, events, map);
+    }
+
+    let triggered: Vec<(Entity, Entity)> = events
+        .iter()
+        .filter_map(|e| match e {
+            Event::Triggered { trigger, by } => Some((*trigger, *by)),
+            _ => None,
+        })
+        .collect();
+
+    for (trigger, by) in triggered {
+        fire(world, events, map, trigger, by);
+    }
+}
+
+pub fn door_at<M: Clone>(world: &World, map: &TileMap<M>, cell: Cell) -> Option<Entity> {
+    world
+        .query::<(&Door, &Position)>()
+        .iter()
+        .find(|(_, (_, position))| map.cell((**position).into()) == cell)
+        .map(|(e, _)| e)
+}
+
+fn is_secret(world: &World, door: Entity) -> bool {
+    world.get::<&Secret>(door).is_ok()
+}
+
+/// Cells blocked by a closed door.
+pub fn closed_doors<M: Clone>(world: &World, map: &TileMap<M>) -> Vec<Cell> {
+    world
+        .query::<(&Door, &Position)>()
+        .iter()
+        .filter(|(_, (door, _))| !door.open)
+        .map(|(_, (_, position))| map.cell((*position).into()))
+        .collect()
+}
+
+fn interact<M: Clone>(world: &mut World, events: &mut Vec<Event>, map: &TileMap<M>) {
+    let mut cmd = CommandBuffer::new();
+
+    let attempts: Vec<(Entity, Cell)> = world
+        .query::<(&Intent, &Orientation, &Position)>()
+        .iter()
+        .filter(|(_, (intent, _, _))| intent.action == Action::Interact)
+        .map(|(e, (_, orientation, position))| {
+            cmd.remove::<(Intent,)>(e);
+            let front = Into::<Vec3>::into(*position)
+                + movement::get_translation(orientation.facing, Direction::Forward, 1.);
+            (e, map.cell(front))
+        })
+        .collect();
+
+    cmd.run_on(world);
+
+    for (entity, cell) in attempts {
+        let switch = world
+            .query::<(&Trigger, &Position)>()
+            .iter()
+            .find(|(_, (trigger, position))| {
+                trigger.kind != TriggerKind::Plate && map.cell((**position).into()) == cell
+            })
+            .map(|(e, _)| e);
+
+        let handled = if let Some(trigger) = switch {
+            events.push(Event::Triggered {
+                trigger,
+                by: entity,
+            });
+            true
+        } else if let Some(door) = door_at(world, map, cell).filter(|d| !is_secret(world, *d)) {
+            if world.get::<&Door>(door).unwrap().locked {
+                events.push(Event::Message("The door is locked".to_string()));
+            } else {
+                operate_door(world, events, map, door, DoorOperation::Toggle);
+            }
+            true
+        } else {
+            false
+        };
+
+        events.push(Event::Interacted {
+            by: entity,
+            cell,
+            handled,
+        });
+    }
+}
+
+/// Update the plates after something moved, a plate is pressed while an
+/// entity stands on it.
+fn press_plates<M: Clone>(world: &mut World, events: &mut Vec<Event>, map: &TileMap<M>) {
+    let occupants: HashMap<Cell, Entity> = world
+        .query::<(&Orientation, &Position)>()
+        .without::<&Projectile>()
+        .iter()
+        .map(|(e, (_, position))| (map.cell((*position).into()), e))
+        .collect();
+
+    world
+        .query_mut::<(&mut Trigger, &Position)>()
+        .into_iter()
+        .filter(|(_, (trigger, _))| trigger.kind == TriggerKind::Plate)
+        .for_each(|(e, (trigger, position))| {
+            let occupant = occupants.get(&map.cell((*position).into()));
+            if let (Some(by), false) = (occupant, trigger.active) {
+                events.push(Event::Triggered {
+                    trigger: e,
+                    by: *by,
+                });
+            }
+            trigger.active = occupant.is_some();
+        });
+}
+
+fn fire<M: Clone>(
+    world: &mut World,
+    events: &mut Vec<Event>,
+    map: &TileMap<M>,
+    entity: Entity,
+    by: Entity,
+) {
+    let Ok(mut trigger) = world.get::<&Trigger>(entity).map(|t| (*t).clone()) else {
+        return;
+    };
+
+    if let Some(item) = &trigger.requires {
+        let held = world
+            .get::<&Inventory>(by)
+            .map(|inventory| inventory.has(item))
+            .unwrap_or(false);
+        if !held {
+            if trigger.kind != TriggerKind::Plate {
+                events.push(Event::Message(format!("You need a {} to use this", item)));
+            }
+            return;
+        }
+    }
+
+    match trigger.kind {
+        TriggerKind::Lever => trigger.active = !trigger.active,
+        TriggerKind::Button => trigger.active = true,
+        TriggerKind::Plate => (),
+    }
+
+    *world.get::<&mut Trigger>(entity).unwrap() = trigger.clone();
+
+    if trigger.once && trigger.fired {
+        return;
+    }
+
+    if let Some(group) = &trigger.group {
+        let ready = world
+            .query::<&Trigger>()
+            .iter()
+            .filter(|(_, t)| t.group.as_ref() == Some(group))
+            .all(|(_, t)| t.active);
+        if !ready {
+            return;
+        }
+    }
+
+    debug!("Trigger {:?} fired", trigger.kind);
+    world.get::<&mut Trigger>(entity).unwrap().fired = true;
+
+    for action in &trigger.actions {
+        match action {
+            TriggerAction::Door(position, operation) => {
+                match door_at(world, map, map.cell(*position)) {
+                    Some(door) => operate_door(world, events, map, door, *operation),
+                    None => warn!("No door at {}", map.cell(*position)),
+                }
+            }
+            TriggerAction::Spawn(position, name, health) => {
+                spawner::spawn_monster(world, *position, name, *health);
+            }
+            TriggerAction::Message(message) => events.push(Event::Message(message.clone())),
+        }
+    }
+}
+
+pub fn operate_door<M: Clone>(
+    world: &mut World,
+    events: &mut Vec<Event>,
+    map: &TileMap<M>,
+    entity: Entity,
+    operation: DoorOperation,
+) {
+    let Ok((door, position)) = world
+        .query_one_mut::<(&Door, &Position)>(entity)
+        .map(|(d, p)| (*d, *p))
+    else {
+        return;
+    };
+
+    let open = match operation {
+        DoorOperation::Open => true,
+        DoorOperation::Close => false,
+        DoorOperation::Toggle => !door.open,
+    };
+
+    if open == door.open {
+        return;
+    }
+
+    let cell = map.cell(position.into());
+    let blocked = world
+        .query::<(&Orientation, &Position)>()
+        .iter()
+        .any(|(_, (_, p))| map.cell((*p).into()) == cell);
+
+    if !open && blocked {
+        events.push(Event::Message("Something blocks the door".to_string()));
+        return;
+    }
+
+    world.get::<&mut Door>(entity).unwrap().open = open;
+    if open {
+        let _ = world.remove_one::<Secret>(entity);
+    }
+    events.push(Event::DoorChanged(entity));
+}
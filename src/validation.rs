@@ -0,0 +1,348 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+
+use crate::map::{Cell, FeatureKind, TileMap, TileSet};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Problem {
+    /// The start is in a wall or has no floor under it.
+    StartBlocked,
+    /// The floor cannot be reached from the start.
+    Unreachable,
+    /// The floor is next to a cell with neither wall nor floor.
+    MissingWall,
+    /// More than one tile at the same position.
+    DuplicateTile,
+    /// Stairs without matching stairs on the next/previous level. Stairs
+    /// leading out of the map (above the first level or below the last one)
+    /// are dungeon exits and are not reported.
+    BrokenStairs,
+    /// Door not placed between two walls.
+    BrokenDoor,
+    /// Ladder or rope above neither floor nor opening.
+    BrokenLadder,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub problem: Problem,
+    pub cell: Cell,
+}
+
+impl Diagnostic {
+    fn error(problem: Problem, cell: Cell) -> Self {
+        Diagnostic {
+            severity: Severity::Error,
+            problem,
+            cell,
+        }
+    }
+
+    fn warning(problem: Problem, cell: Cell) -> Self {
+        Diagnostic {
+            severity: Severity::Warning,
+            problem,
+            cell,
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+
+        let message = match self.problem {
+            Problem::StartBlocked => "start position is not walkable",
+            Problem::Unreachable => "floor is not reachable from start",
+            Problem::MissingWall => "floor is not enclosed by walls",
+            Problem::DuplicateTile => "duplicate tile",
+            Problem::BrokenStairs => "stairs are not linked to another level",
+            Problem::BrokenDoor => "door is not set in a wall",
+            Problem::BrokenLadder => "ladder or rope does not lead down to a floor",
+        };
+
+        write!(f, "{}: {} at {}", severity, message, self.cell)
+    }
+}
+
+/// Occupancy of every cell, built once so that checks don't have to scan
+/// the tile list.
+struct Cells {
+    walls: HashSet<Cell>,
+    floors: HashSet<Cell>,
+    illusions: HashSet<Cell>,
+    /// Holes, ladders and ropes
+    open: HashSet<Cell>,
+    climbable: HashSet<Cell>,
+}
+
+impl Cells {
+    fn walkable(&self, cell: &Cell) -> bool {
+        self.floors.contains(cell) && (!self.walls.contains(cell) || self.illusions.contains(cell))
+    }
+
+    fn empty(&self, cell: &Cell) -> bool {
+        !self.floors.contains(cell) && !self.walls.contains(cell) && !self.open.contains(cell)
+    }
+}
+
+/// Run all checks on a map, returning diagnostics sorted by cell.
+pub fn validate<M: Clone>(map: &TileMap<M>) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    let cells = check_duplicates(map, &mut diagnostics);
+
+    check_connectivity(map, &cells, &mut diagnostics);
+    check_boundaries(&cells, &mut diagnostics);
+    check_stairs(map, &cells, &mut diagnostics);
+    check_doors(map, &cells, &mut diagnostics);
+    check_ladders(&cells, &mut diagnostics);
+
+    diagnostics.sort_by_key(|d| (d.cell.level, d.cell.y, d.cell.x));
+
+    diagnostics
+}
+
+pub fn has_errors(diagnostics: &[Diagnostic]) -> bool {
+    diagnostics.iter().any(|d| d.severity == Severity::Error)
+}
+
+fn check_duplicates<M: Clone>(map: &TileMap<M>, diagnostics: &mut Vec<Diagnostic>) -> Cells {
+    let mut count = HashMap::new();
+
+    let mut cells = Cells {
+        walls: HashSet::new(),
+        floors: HashSet::new(),
+        illusions: map
+            .features(FeatureKind::Illusion)
+            .map(|f| map.cell(f.position))
+            .collect(),
+        open: HashSet::new(),
+        climbable: map
+            .features(FeatureKind::Ladder)
+            .chain(map.features(FeatureKind::Rope))
+            .map(|f| map.cell(f.position))
+            .collect(),
+    };
+
+    cells.open = map
+        .features(FeatureKind::Hole)
+        .map(|f| map.cell(f.position))
+        .chain(cells.climbable.iter().copied())
+        .collect();
+
+    for tile in &map.tiles {
+        let cell = map.cell(tile.position);
+        let is_wall = matches!(tile.tile, TileSet::WALL(_));
+
+        if is_wall {
+            cells.walls.insert(cell);
+        } else {
+            cells.floors.insert(cell);
+        }
+
+        *count.entry((cell, is_wall)).or_insert(0) += 1;
+    }
+
+    for ((cell, _), n) in count {
+        if n > 1 {
+            diagnostics.push(Diagnostic::warning(Problem::DuplicateTile, cell));
+        }
+    }
+
+    cells
+}
+
+fn check_connectivity<M: Clone>(
+    map: &TileMap<M>,
+    cells: &Cells,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let start = map.cell(map.start);
+
+    if !cells.walkable(&start) {
+        diagnostics.push(Diagnostic::error(Problem::StartBlocked, start));
+        return;
+    }
+
+    let mut visited = HashSet::new();
+    let mut queue = VecDeque::new();
+
+    visited.insert(start);
+    queue.push_back(start);
+
+    while let Some(cell) = queue.pop_front() {
+        // Nothing but falling out of a hole
+        let mut next = if cells.open.contains(&cell) && !cells.climbable.contains(&cell) {
+            Vec::new()
+        } else {
+            cell.neighbours().to_vec()
+        };
+
+        if cells.open.contains(&cell) {
+            next.push(cell.below());
+        }
+        if cells.climbable.contains(&cell.above()) {
+            next.push(cell.above());
+        }
+
+        if map.feature_at(FeatureKind::StairsDown, cell) {
+            next.push(Cell::new(cell.x, cell.y, cell.level + 1));
+        }
+        if map.feature_at(FeatureKind::StairsUp, cell) {
+            next.push(Cell::new(cell.x, cell.y, cell.level - 1));
+        }
+
+        for n in next {
+            let passable = cells.walkable(&n) || cells.open.contains(&n);
+            if passable && visited.insert(n) {
+                queue.push_back(n);
+            }
+        }
+    }
+
+    for cell in &cells.floors {
+        if cells.walkable(cell) && !visited.contains(cell) {
+            diagnostics.push(Diagnostic::warning(Problem::Unreachable, *cell));
+        }
+    }
+}
+
+fn check_boundaries(cells: &Cells, diagnostics: &mut Vec<Diagnostic>) {
+    for cell in &cells.floors {
+        if cells.walkable(cell) && cell.neighbours().iter().any(|n| cells.empty(n)) {
+            diagnostics.push(Diagnostic::error(Problem::MissingWall, *cell));
+        }
+    }
+}
+
+fn check_stairs<M: Clone>(map: &TileMap<M>, cells: &Cells, diagnostics: &mut Vec<Diagnostic>) {
+    let levels: HashSet<i32> = cells.floors.iter().map(|c| c.level).collect();
+
+    for (kind, other, step) in [
+        (FeatureKind::StairsDown, FeatureKind::StairsUp, 1),
+        (FeatureKind::StairsUp, FeatureKind::StairsDown, -1),
+    ] {
+        for stairs in map.features(kind) {
+            let cell = map.cell(stairs.position);
+            let target = Cell::new(cell.x, cell.y, cell.level + step);
+
+            if levels.contains(&target.level) && !map.feature_at(other, target) {
+                diagnostics.push(Diagnostic::error(Problem::BrokenStairs, cell));
+            }
+        }
+    }
+}
+
+fn check_doors<M: Clone>(map: &TileMap<M>, cells: &Cells, diagnostics: &mut Vec<Diagnostic>) {
+    let doors = map
+        .features(FeatureKind::Door)
+        .chain(map.features(FeatureKind::SecretDoor));
+
+    for door in doors {
+        let cell = map.cell(door.position);
+        let [north, east, south, west] = cell.neighbours();
+
+        let linked = |a: &Cell, b: &Cell, c: &Cell, d: &Cell| {
+            cells.walls.contains(a)
+                && cells.walls.contains(b)
+                && cells.walkable(c)
+                && cells.walkable(d)
+        };
+
+        if !linked(&north, &south, &east, &west) && !linked(&east, &west, &north, &south) {
+            diagnostics.push(Diagnostic::error(Problem::BrokenDoor, cell));
+        }
+    }
+}
+
+fn check_ladders(cells: &Cells, diagnostics: &mut Vec<Diagnostic>) {
+    for cell in &cells.climbable {
+        let below = cell.below();
+        if !cells.walkable(&below) && !cells.open.contains(&below) {
+            diagnostics.push(Diagnostic::error(Problem::BrokenLadder, *cell));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::TileSet;
+
+    fn load(data: &str) -> TileMap<()> {
+        let mut map = TileMap::new();
+        map.load(data, (), ()).unwrap();
+        map
+    }
+
+    fn problems(map: &TileMap<()>) -> Vec<Problem> {
+        validate(map).iter().map(|d| d.problem).collect()
+    }
+
+    #[test]
+    fn valid_map() {
+        let map = load("wwwww\nw@>.w\nwwwww\n---\nwwwww\nwo<.w\nwwwww\n---\nwwwww\nw...w\nwwwww\n");
+        assert_eq!(problems(&map), vec![]);
+    }
+
+    #[test]
+    fn start_blocked() {
+        let mut map = load("www\nw@w\nwww\n");
+        map.start = map.position(Cell::new(0, 0, 0));
+        assert_eq!(problems(&map), vec![Problem::StartBlocked]);
+    }
+
+    #[test]
+    fn unreachable() {
+        let map = load("wwwww\nw@w.w\nwwwww\n");
+        assert_eq!(problems(&map), vec![Problem::Unreachable]);
+    }
+
+    #[test]
+    fn missing_wall() {
+        let map = load("www\nw@.\nwww\n");
+        assert_eq!(problems(&map), vec![Problem::MissingWall]);
+    }
+
+    #[test]
+    fn duplicate_tile() {
+        let mut map = load("www\nw@w\nwww\n");
+        map.add_tile(TileSet::WALL(()), map.position(Cell::new(0, 0, 0)));
+        assert_eq!(problems(&map), vec![Problem::DuplicateTile]);
+    }
+
+    #[test]
+    fn broken_stairs() {
+        let map = load("wwww\nw@>w\nwwww\n---\nwwww\nw..w\nwwww\n");
+        assert_eq!(problems(&map), vec![Problem::BrokenStairs]);
+    }
+
+    #[test]
+    fn stairs_out_of_the_dungeon() {
+        let map = load("wwww\nw@>w\nwwww\n");
+        assert_eq!(problems(&map), vec![]);
+    }
+
+    #[test]
+    fn broken_door() {
+        let map = load("wwwww\nw@+.w\nw...w\nwwwww\n");
+        assert_eq!(problems(&map), vec![Problem::BrokenDoor]);
+    }
+
+    #[test]
+    fn broken_ladder() {
+        let map = load("wwww\nw@hw\nwwww\n");
+        assert_eq!(problems(&map), vec![Problem::BrokenLadder]);
+    }
+}
@@ -0,0 +1,90 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver};
+
+use anyhow::{anyhow, Result};
+use log::*;
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher as _};
+
+/// Source asset directory, watched in development mode: `assets` in the
+/// working directory, which is the crate root when run through cargo.
+pub fn source_dir() -> PathBuf {
+    PathBuf::from(crate::ASSETS_DIR)
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Change {
+    Map,
+    Script,
+    Asset(PathBuf),
+}
+
+/// Watch the current map file and the asset directory for changes.
+/// Directories are watched rather than files so that editors replacing the
+/// file on save are handled.
+pub struct Watcher {
+    _watcher: RecommendedWatcher,
+    events: Receiver<notify::Result<notify::Event>>,
+    map: PathBuf,
+    assets: PathBuf,
+}
+
+impl Watcher {
+    pub fn new(map: &Path) -> Result<Self> {
+        let (tx, events) = channel();
+
+        let mut watcher = notify::recommended_watcher(tx)?;
+
+        let map = map.canonicalize()?;
+        let assets = source_dir()
+            .canonicalize()
+            .map_err(|e| anyhow!("{}: {}", crate::ASSETS_DIR, e))?;
+
+        watcher.watch(&assets, RecursiveMode::NonRecursive)?;
+        if let Some(dir) = map.parent() {
+            if dir != assets {
+                watcher.watch(dir, RecursiveMode::NonRecursive)?;
+            }
+        }
+
+        info!("Watching {} and {}", map.display(), assets.display());
+
+        Ok(Watcher {
+            _watcher: watcher,
+            events,
+            map,
+            assets,
+        })
+    }
+
+    /// Changes since the last call, each reported once.
+    pub fn poll(&self) -> HashSet<Change> {
+        let mut changes = HashSet::new();
+
+        for event in self.events.try_iter() {
+            let event = match event {
+                Ok(event) => event,
+                Err(e) => {
+                    warn!("Watch error: {}", e);
+                    continue;
+                }
+            };
+
+            if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                continue;
+            }
+
+            for path in event.paths {
+                if path == self.map {
+                    changes.insert(Change::Map);
+                } else if path.extension().is_some_and(|e| e == "rhai") {
+                    changes.insert(Change::Script);
+                } else if path.parent() == Some(self.assets.as_path()) {
+                    changes.insert(Change::Asset(path));
+                }
+            }
+        }
+
+        changes
+    }
+}